@@ -1,29 +1,326 @@
-use crate::collision::{Collidable, Collision, Ray};
-use crate::images::{RawImage, BLACK};
-use crate::material::Material;
-use crate::space::{scene::Scene, Point};
+#[cfg(test)]
+mod tests;
 
+pub mod stereo;
+
+use crate::collision::Ray;
+use crate::images::{FloatColor, FloatImage, RawImage};
+use crate::render::aov::{render_aovs, AovBuffers};
+use crate::render::context::RenderContext;
+use crate::render::progressive::ProgressiveRenderer;
+use crate::render::stats::Phase;
+use crate::render::{render_image, render_image_pass, sample_margin, RenderGeometry};
+use crate::space::frustum::{Frustum, Plane};
+use crate::space::{scene::Scene, Point, Vector, ORIGIN};
+
+use std::f64::consts::PI;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
 pub enum Camera {
-    Ortho(OrthographicCamera)
+    Ortho(OrthographicCamera),
+    Perspective(PerspectiveCamera),
+    Equirectangular(EquirectangularCamera),
+    Fisheye(FisheyeCamera)
 }
 
 impl Camera {
     pub fn new_default() -> Camera {
-        // TODO: The default camera should be perspective, but that doesn't exist yet.
-        Camera::Ortho(OrthographicCamera::new_default())
+        Camera::Perspective(PerspectiveCamera::new_default())
+    }
+
+    /// Creates the ray that passes through the given position on the image.
+    /// Both `x` and `y` go from 0 to 1, starting in the top-left corner.
+    pub fn primary_ray(&self, x: f64, y: f64) -> Ray {
+        match self {
+            Camera::Ortho(camera) => camera.primary_ray(x, y),
+            Camera::Perspective(camera) => camera.primary_ray(x, y),
+            Camera::Equirectangular(camera) => camera.primary_ray(x, y),
+            Camera::Fisheye(camera) => camera.primary_ray(x, y)
+        }
+    }
+
+    /// Like [`Camera::primary_ray`], but starting from a point on the lens
+    /// picked by `lens_sample`, whose parts both go from 0 to 1.
+    pub fn lens_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Ray {
+        match self {
+            Camera::Ortho(camera) => camera.lens_ray(x, y, lens_sample),
+            Camera::Perspective(camera) => camera.lens_ray(x, y, lens_sample),
+            Camera::Equirectangular(camera) => camera.lens_ray(x, y, lens_sample),
+            Camera::Fisheye(camera) => camera.lens_ray(x, y, lens_sample)
+        }
+    }
+
+    /// Like [`Camera::lens_ray`], but gives `None` for positions on the image
+    /// that the camera can't see, like the corners of a fisheye image.
+    pub fn image_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Option<Ray> {
+        match self {
+            Camera::Fisheye(camera) => camera.image_ray(x, y, lens_sample),
+            _ => Some(self.lens_ray(x, y, lens_sample))
+        }
+    }
+
+    /// The space this camera can see, for leaving out anything that can't be
+    /// seen. `margin` widens it on every side by that fraction of the image's
+    /// width or height, for rays that are cast a little outside the image.
+    /// Returns `None` when the camera can see too much to be worth culling,
+    /// like panoramic cameras and cameras with depth of field.
+    pub fn frustum(&self, margin: f64) -> Option<Frustum> {
+        match self {
+            Camera::Ortho(camera) => camera.frustum(margin),
+            Camera::Perspective(camera) => camera.frustum(margin),
+            Camera::Equirectangular(_) | Camera::Fisheye(_) => None
+        }
+    }
+
+    pub fn lens(&self) -> &Lens {
+        match self {
+            Camera::Ortho(camera) => camera.lens(),
+            Camera::Perspective(camera) => camera.lens(),
+            Camera::Equirectangular(camera) => camera.lens(),
+            Camera::Fisheye(camera) => camera.lens()
+        }
+    }
+
+    pub fn set_lens(&mut self, lens: Lens) {
+        match self {
+            Camera::Ortho(camera) => camera.set_lens(lens),
+            Camera::Perspective(camera) => camera.set_lens(lens),
+            Camera::Equirectangular(camera) => camera.set_lens(lens),
+            Camera::Fisheye(camera) => camera.set_lens(lens)
+        }
+    }
+}
+
+/// Which way a camera is facing. This is made up of the direction the camera is
+/// looking, which way is up, and how far the camera is rolled around the
+/// direction it is looking.
+#[derive(Debug, Clone, Copy)]
+pub struct Orientation {
+    direction: Vector,
+    up: Vector,
+    /// The rotation around the view direction in radians. Positive values roll
+    /// the top of the camera towards its right side.
+    roll: f64
+}
+
+impl Orientation {
+    pub fn new(direction: Vector, up: Vector, roll: f64) -> Self {
+        assert_ne!(direction, ORIGIN);
+
+        Orientation {
+            direction: direction.normalized(),
+            up: up.normalized(),
+            roll
+        }
+    }
+
+    /// Looking down the positive z axis with positive y being up.
+    pub fn new_default() -> Self {
+        Orientation::new(Point::new(0.0, 0.0, 1.0), Point::new(0.0, 1.0, 0.0), 0.0)
+    }
+
+    pub fn direction(&self) -> &Vector {
+        &self.direction
+    }
+
+    pub fn set_direction(&mut self, direction: Vector) {
+        assert_ne!(direction, ORIGIN);
+        self.direction = direction.normalized();
+    }
+
+    pub fn up(&self) -> &Vector {
+        &self.up
+    }
+
+    pub fn set_up(&mut self, up: Vector) {
+        self.up = up.normalized();
+    }
+
+    pub fn roll(&self) -> f64 {
+        self.roll
+    }
+
+    pub fn set_roll(&mut self, roll: f64) {
+        self.roll = roll;
+    }
+
+    /// Calculates the right, up, and forward directions of the camera as unit
+    /// vectors, in that order. Unlike [`Orientation::up`] the up direction
+    /// returned here is always perpendicular to the view direction and has the
+    /// roll applied to it.
+    pub fn basis(&self) -> (Vector, Vector, Vector) {
+        let forward = self.direction;
+        let mut right = self.up.cross(&forward);
+        // When looking straight along the up vector there is no way to tell
+        // which way is right, so just pick something perpendicular.
+        if right == ORIGIN {
+            right = Point::new(0.0, 0.0, 1.0).cross(&forward);
+            if right == ORIGIN {
+                right = Point::new(0.0, 1.0, 0.0).cross(&forward);
+            }
+        }
+        let right = right.normalized();
+        let up = forward.cross(&right);
+
+        let (sin, cos) = self.roll.sin_cos();
+        let rolled_right = right * cos - up * sin;
+        let rolled_up = up * cos + right * sin;
+
+        (rolled_right, rolled_up, forward)
+    }
+}
+
+/// The lens of a camera, which controls depth of field. Rays start from
+/// random points across the lens and all meet again at the focus distance, so
+/// anything at that distance is sharp while things nearer or further away get
+/// blurrier the larger the aperture is.
+#[derive(Debug, Clone, Copy)]
+pub struct Lens {
+    /// The radius of the lens. Zero makes a pinhole camera where everything is
+    /// in focus.
+    aperture: f64,
+    /// How far in front of the camera things are perfectly in focus, measured
+    /// along the direction the camera is looking.
+    focus_distance: f64
+}
+
+impl Lens {
+    pub fn new(aperture: f64, focus_distance: f64) -> Self {
+        Lens {
+            aperture: aperture.max(0.0),
+            focus_distance
+        }
+    }
+
+    /// A pinhole lens, so nothing is blurred.
+    pub fn new_pinhole() -> Self {
+        Lens::new(0.0, 1.0)
+    }
+
+    pub fn aperture(&self) -> f64 {
+        self.aperture
+    }
+
+    pub fn set_aperture(&mut self, aperture: f64) {
+        self.aperture = aperture.max(0.0);
+    }
+
+    pub fn focus_distance(&self) -> f64 {
+        self.focus_distance
+    }
+
+    pub fn set_focus_distance(&mut self, focus_distance: f64) {
+        self.focus_distance = focus_distance;
+    }
+
+    /// Moves the start of a ray to a point on the lens while keeping where it
+    /// crosses the focus distance the same. `direction` is the way the ray
+    /// would go from the center of the lens, scaled so that it moves one unit
+    /// forward for each unit along it.
+    fn focus_ray(
+        &self,
+        origin: Point,
+        direction: Vector,
+        basis: (Vector, Vector, Vector),
+        lens_sample: (f64, f64)
+    ) -> Ray {
+        if self.aperture <= 0.0 {
+            return Ray::new(origin, direction);
+        }
+
+        let (right, up, _) = basis;
+        let focus_point = origin + direction * self.focus_distance;
+        let (dx, dy) = sample_disk(lens_sample);
+        let lens_origin = origin + right * (dx * self.aperture) + up * (dy * self.aperture);
+
+        Ray::new(lens_origin, focus_point - lens_origin)
     }
 }
 
+/// Maps a point in the unit square onto the unit disk using Shirley and
+/// Chiu's concentric mapping, which keeps evenly spread points evenly spread.
+/// The center of the square goes to the center of the disk.
+fn sample_disk(sample: (f64, f64)) -> (f64, f64) {
+    let x = 2.0 * sample.0 - 1.0;
+    let y = 2.0 * sample.1 - 1.0;
+    if x == 0.0 && y == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (radius, angle) = if x.abs() > y.abs() {
+        (x, std::f64::consts::FRAC_PI_4 * (y / x))
+    } else {
+        (
+            y,
+            std::f64::consts::FRAC_PI_2 - std::f64::consts::FRAC_PI_4 * (x / y)
+        )
+    };
+
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+/// Something that has a location and can be pointed in different directions.
+pub trait Orientable {
+    fn location(&self) -> &Point;
+    fn orientation(&self) -> &Orientation;
+    fn orientation_mut(&mut self) -> &mut Orientation;
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        *self.orientation_mut() = orientation;
+    }
+
+    /// Turns to face the target while keeping the same up vector and roll.
+    fn look_at(&mut self, target: &Point) {
+        let direction = target - self.location();
+        self.orientation_mut().set_direction(direction);
+    }
+}
+
+impl Orientable for Camera {
+    fn location(&self) -> &Point {
+        match self {
+            Camera::Ortho(camera) => camera.location(),
+            Camera::Perspective(camera) => camera.location(),
+            Camera::Equirectangular(camera) => camera.location(),
+            Camera::Fisheye(camera) => camera.location()
+        }
+    }
+
+    fn orientation(&self) -> &Orientation {
+        match self {
+            Camera::Ortho(camera) => camera.orientation(),
+            Camera::Perspective(camera) => camera.orientation(),
+            Camera::Equirectangular(camera) => camera.orientation(),
+            Camera::Fisheye(camera) => camera.orientation()
+        }
+    }
+
+    fn orientation_mut(&mut self) -> &mut Orientation {
+        match self {
+            Camera::Ortho(camera) => camera.orientation_mut(),
+            Camera::Perspective(camera) => camera.orientation_mut(),
+            Camera::Equirectangular(camera) => camera.orientation_mut(),
+            Camera::Fisheye(camera) => camera.orientation_mut()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct OrthographicCamera {
     location: Point,
+    orientation: Orientation,
+    lens: Lens,
     width: f64,
-    height: f64 // TODO: Direction, OutputImageSpec
+    height: f64 // TODO: OutputImageSpec
 }
 
 impl OrthographicCamera {
     pub fn new(location: Point, width: f64, height: f64) -> Self {
         OrthographicCamera {
             location,
+            orientation: Orientation::new_default(),
+            lens: Lens::new_pinhole(),
             width,
             height
         }
@@ -32,69 +329,676 @@ impl OrthographicCamera {
     pub fn new_default() -> Self {
         OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 1.0, 1.0)
     }
+
+    /// How wide the area the camera sees is.
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// How tall the area the camera sees is.
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    pub fn lens(&self) -> &Lens {
+        &self.lens
+    }
+
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+    }
+
+    /// Creates the ray that passes through the given position on the image.
+    /// Both `x` and `y` go from 0 to 1, starting in the top-left corner.
+    pub fn primary_ray(&self, x: f64, y: f64) -> Ray {
+        self.lens_ray(x, y, (0.5, 0.5))
+    }
+
+    /// Like [`OrthographicCamera::primary_ray`], but starting from a point on
+    /// the lens picked by `lens_sample`, whose parts both go from 0 to 1.
+    pub fn lens_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Ray {
+        let basis = self.orientation.basis();
+        let (right, up, forward) = basis;
+        let x = (x - 0.5) * self.width;
+        let y = (0.5 - y) * self.height;
+
+        let origin = self.location + right * x + up * y;
+        self.lens.focus_ray(origin, forward, basis, lens_sample)
+    }
+
+    /// The box the camera can see, going on forever in front of it. See
+    /// [`Camera::frustum`].
+    pub fn frustum(&self, margin: f64) -> Option<Frustum> {
+        if self.lens.aperture() > 0.0 {
+            return None;
+        }
+
+        let (right, up, forward) = self.orientation.basis();
+        let half_width = self.width / 2.0 * (1.0 + 2.0 * margin);
+        let half_height = self.height / 2.0 * (1.0 + 2.0 * margin);
+
+        Some(Frustum::new(vec![
+            Plane::new(self.location, forward),
+            Plane::new(self.location - right * half_width, right),
+            Plane::new(self.location + right * half_width, right * -1.0),
+            Plane::new(self.location - up * half_height, up),
+            Plane::new(self.location + up * half_height, up * -1.0),
+        ]))
+    }
+}
+
+impl Orientable for OrthographicCamera {
+    fn location(&self) -> &Point {
+        &self.location
+    }
+
+    fn orientation(&self) -> &Orientation {
+        &self.orientation
+    }
+
+    fn orientation_mut(&mut self) -> &mut Orientation {
+        &mut self.orientation
+    }
+}
+
+/// A camera where all rays start at a single point and spread out, so objects
+/// that are further away appear smaller.
+#[derive(Debug, Clone)]
+pub struct PerspectiveCamera {
+    location: Point,
+    orientation: Orientation,
+    lens: Lens,
+    /// The vertical field of view in radians.
+    fov: f64,
+    /// The width of the image divided by its height.
+    aspect_ratio: f64,
+    /// How far the image is moved to the right and up without turning the
+    /// camera, as a fraction of its width and height. This gives off-axis
+    /// views where the camera isn't looking at the center of the image.
+    shift: (f64, f64)
+}
+
+impl PerspectiveCamera {
+    pub fn new(location: Point, target: Point, up: Vector, fov: f64, aspect_ratio: f64) -> Self {
+        assert_ne!(location, target);
+
+        PerspectiveCamera {
+            location,
+            orientation: Orientation::new(target - location, up, 0.0),
+            // Focus on whatever the camera was pointed at
+            lens: Lens::new(0.0, (target - location).length()),
+            fov,
+            aspect_ratio,
+            shift: (0.0, 0.0)
+        }
+    }
+
+    /// A camera at the origin looking down the positive z axis with a 60 degree
+    /// field of view and a square image.
+    pub fn new_default() -> Self {
+        PerspectiveCamera::new(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 1.0, 0.0),
+            std::f64::consts::FRAC_PI_3,
+            1.0
+        )
+    }
+
+    pub fn fov(&self) -> f64 {
+        self.fov
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    pub fn lens(&self) -> &Lens {
+        &self.lens
+    }
+
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+    }
+
+    pub fn shift(&self) -> (f64, f64) {
+        self.shift
+    }
+
+    pub fn set_shift(&mut self, shift: (f64, f64)) {
+        self.shift = shift;
+    }
+
+    /// Creates the ray that passes through the given position on the image.
+    /// Both `x` and `y` go from 0 to 1, starting in the top-left corner.
+    pub fn primary_ray(&self, x: f64, y: f64) -> Ray {
+        self.lens_ray(x, y, (0.5, 0.5))
+    }
+
+    /// Like [`PerspectiveCamera::primary_ray`], but starting from a point on
+    /// the lens picked by `lens_sample`, whose parts both go from 0 to 1.
+    pub fn lens_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Ray {
+        let basis = self.orientation.basis();
+        let (right, up, forward) = basis;
+        let half_height = (self.fov / 2.0).tan();
+        let half_width = half_height * self.aspect_ratio;
+
+        let (shift_x, shift_y) = self.shift;
+
+        let direction = forward
+            + right * ((2.0 * (x + shift_x) - 1.0) * half_width)
+            + up * ((1.0 - 2.0 * (y - shift_y)) * half_height);
+
+        self.lens
+            .focus_ray(self.location, direction, basis, lens_sample)
+    }
+
+    /// The pyramid the camera can see, going on forever in front of it. See
+    /// [`Camera::frustum`].
+    pub fn frustum(&self, margin: f64) -> Option<Frustum> {
+        if self.lens.aperture() > 0.0 {
+            return None;
+        }
+
+        let (right, up, forward) = self.orientation.basis();
+        let half_height = (self.fov / 2.0).tan();
+        let half_width = half_height * self.aspect_ratio;
+        let (shift_x, shift_y) = self.shift;
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| {
+            forward
+                + right * ((x * (1.0 + 2.0 * margin) + 2.0 * shift_x) * half_width)
+                + up * ((y * (1.0 + 2.0 * margin) + 2.0 * shift_y) * half_height)
+        });
+
+        // With a big enough shift the camera's own direction is outside of the
+        // view, but the middle of the image never is
+        let inside = corners[0] + corners[1] + corners[2] + corners[3];
+
+        let mut planes = vec![Plane::new(self.location, forward)];
+        for k in 0..4 {
+            // Each side goes through the camera and two neighbouring corners
+            let mut normal = corners[k].cross(&corners[(k + 1) % 4]);
+            if normal.dot(&inside) < 0.0 {
+                normal = normal * -1.0;
+            }
+            planes.push(Plane::new(self.location, normal));
+        }
+
+        Some(Frustum::new(planes))
+    }
+}
+
+impl Orientable for PerspectiveCamera {
+    fn location(&self) -> &Point {
+        &self.location
+    }
+
+    fn orientation(&self) -> &Orientation {
+        &self.orientation
+    }
+
+    fn orientation_mut(&mut self) -> &mut Orientation {
+        &mut self.orientation
+    }
+}
+
+/// A camera that sees in every direction around a single point, making an
+/// equirectangular panorama. The left and right edges of the image are directly
+/// behind the camera, the top and bottom edges are straight up and down, and
+/// the center of the image is the direction the camera is facing.
+///
+/// The lens focuses on a sphere around the camera rather than a plane, so
+/// everything at the focus distance is sharp no matter which way it is.
+#[derive(Debug, Clone)]
+pub struct EquirectangularCamera {
+    location: Point,
+    orientation: Orientation,
+    lens: Lens
+}
+
+impl EquirectangularCamera {
+    pub fn new(location: Point, orientation: Orientation) -> Self {
+        EquirectangularCamera {
+            location,
+            orientation,
+            lens: Lens::new_pinhole()
+        }
+    }
+
+    /// A camera at the origin facing down the positive z axis.
+    pub fn new_default() -> Self {
+        EquirectangularCamera::new(ORIGIN, Orientation::new_default())
+    }
+
+    pub fn lens(&self) -> &Lens {
+        &self.lens
+    }
+
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+    }
+
+    /// Creates the ray that passes through the given position on the image.
+    /// Both `x` and `y` go from 0 to 1, starting in the top-left corner.
+    pub fn primary_ray(&self, x: f64, y: f64) -> Ray {
+        self.lens_ray(x, y, (0.5, 0.5))
+    }
+
+    /// Like [`EquirectangularCamera::primary_ray`], but starting from a point
+    /// on the lens picked by `lens_sample`, whose parts both go from 0 to 1.
+    pub fn lens_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Ray {
+        let (right, up, forward) = self.orientation.basis();
+        let longitude = (x - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - y) * PI;
+
+        let direction = forward * (latitude.cos() * longitude.cos())
+            + right * (latitude.cos() * longitude.sin())
+            + up * latitude.sin();
+
+        spherical_focus_ray(&self.lens, self.location, direction, &up, lens_sample)
+    }
+}
+
+impl Orientable for EquirectangularCamera {
+    fn location(&self) -> &Point {
+        &self.location
+    }
+
+    fn orientation(&self) -> &Orientation {
+        &self.orientation
+    }
+
+    fn orientation_mut(&mut self) -> &mut Orientation {
+        &mut self.orientation
+    }
+}
+
+/// A camera with an equidistant fisheye lens, where the distance from the
+/// center of the image is proportional to the angle away from the direction
+/// the camera is facing. The field of view fills a circle that touches the top
+/// and bottom of the image, and nothing is seen outside of it.
+///
+/// Like [`EquirectangularCamera`], the lens focuses on a sphere around the
+/// camera.
+#[derive(Debug, Clone)]
+pub struct FisheyeCamera {
+    location: Point,
+    orientation: Orientation,
+    lens: Lens,
+    /// The angle across the whole image circle in radians. This can be more
+    /// than 180 degrees to see behind the camera.
+    fov: f64,
+    /// The width of the image divided by its height.
+    aspect_ratio: f64
+}
+
+impl FisheyeCamera {
+    pub fn new(location: Point, target: Point, up: Vector, fov: f64, aspect_ratio: f64) -> Self {
+        assert_ne!(location, target);
+
+        FisheyeCamera {
+            location,
+            orientation: Orientation::new(target - location, up, 0.0),
+            lens: Lens::new(0.0, (target - location).length()),
+            fov,
+            aspect_ratio
+        }
+    }
+
+    /// A camera at the origin looking down the positive z axis with a 180
+    /// degree field of view and a square image.
+    pub fn new_default() -> Self {
+        FisheyeCamera::new(
+            ORIGIN,
+            Point::new(0.0, 0.0, 1.0),
+            Point::new(0.0, 1.0, 0.0),
+            PI,
+            1.0
+        )
+    }
+
+    pub fn fov(&self) -> f64 {
+        self.fov
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    pub fn lens(&self) -> &Lens {
+        &self.lens
+    }
+
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+    }
+
+    /// Where the position on the image is relative to the center of the image
+    /// circle, scaled so that the edge of the circle is at a distance of one.
+    fn circle_position(&self, x: f64, y: f64) -> (f64, f64) {
+        ((2.0 * x - 1.0) * self.aspect_ratio, 1.0 - 2.0 * y)
+    }
+
+    /// Checks whether the position on the image is inside the image circle.
+    pub fn covers(&self, x: f64, y: f64) -> bool {
+        let (u, v) = self.circle_position(x, y);
+        u * u + v * v <= 1.0
+    }
+
+    /// Creates the ray that passes through the given position on the image.
+    /// Both `x` and `y` go from 0 to 1, starting in the top-left corner.
+    /// Positions outside the image circle carry on getting further from the
+    /// view direction at the same rate.
+    pub fn primary_ray(&self, x: f64, y: f64) -> Ray {
+        self.lens_ray(x, y, (0.5, 0.5))
+    }
+
+    /// Like [`FisheyeCamera::primary_ray`], but starting from a point on the
+    /// lens picked by `lens_sample`, whose parts both go from 0 to 1.
+    pub fn lens_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Ray {
+        let (right, up, forward) = self.orientation.basis();
+        let (u, v) = self.circle_position(x, y);
+        let distance = (u * u + v * v).sqrt();
+
+        let direction = if distance == 0.0 {
+            forward
+        } else {
+            let angle = distance * self.fov / 2.0;
+            let sideways = (right * u + up * v) / distance;
+            forward * angle.cos() + sideways * angle.sin()
+        };
+
+        spherical_focus_ray(&self.lens, self.location, direction, &up, lens_sample)
+    }
+
+    /// Like [`FisheyeCamera::lens_ray`], but gives `None` outside of the image
+    /// circle.
+    pub fn image_ray(&self, x: f64, y: f64, lens_sample: (f64, f64)) -> Option<Ray> {
+        if self.covers(x, y) {
+            Some(self.lens_ray(x, y, lens_sample))
+        } else {
+            None
+        }
+    }
+}
+
+impl Orientable for FisheyeCamera {
+    fn location(&self) -> &Point {
+        &self.location
+    }
+
+    fn orientation(&self) -> &Orientation {
+        &self.orientation
+    }
+
+    fn orientation_mut(&mut self) -> &mut Orientation {
+        &mut self.orientation
+    }
+}
+
+/// Focuses a ray going in any direction from `origin`, with the lens facing
+/// along the ray, so that everything at the focus distance from the origin is
+/// in focus.
+fn spherical_focus_ray(
+    lens: &Lens,
+    origin: Point,
+    direction: Vector,
+    up: &Vector,
+    lens_sample: (f64, f64)
+) -> Ray {
+    let direction = direction.normalized();
+    let basis = Orientation::new(direction, *up, 0.0).basis();
+    lens.focus_ray(origin, direction, basis, lens_sample)
 }
 
 pub trait Renderer {
-    fn render(&self, scene: &Scene, image_size: (usize, usize)) -> RawImage;
+    /// Renders the scene into a high dynamic range image, before any tone
+    /// mapping. Progress is reported to the context as the render goes. If the
+    /// context gets cancelled the render stops early, and the parts of the
+    /// image that weren't finished are left transparent.
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage;
+
+    /// Renders the scene into a high dynamic range image, before any tone
+    /// mapping.
+    fn render_hdr(&self, scene: &Scene, image_size: (usize, usize)) -> FloatImage {
+        self.render_hdr_with_context(scene, image_size, &RenderContext::new_default())
+    }
+
+    /// Like [`Renderer::render`], but keeping the context up to date with how
+    /// the render is going.
+    fn render_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> RawImage {
+        let hdr = self.render_hdr_with_context(scene, image_size, context);
+        let start = Instant::now();
+        let image = hdr.to_raw_image(&scene.render_settings.tone_mapping);
+        context.add_phase_time(Phase::ToneMapping, start.elapsed());
+        image
+    }
+
+    /// Renders the scene and tone maps it into an 8-bit image using the tone
+    /// mapping in the scene's render settings.
+    fn render(&self, scene: &Scene, image_size: (usize, usize)) -> RawImage {
+        self.render_with_context(scene, image_size, &RenderContext::new_default())
+    }
+
+    /// Renders the auxiliary output buffers, like depth and normals, for the
+    /// same view as [`Renderer::render`].
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers;
 }
 
 impl Renderer for Camera {
-    fn render(&self, scene: &Scene, image_size: (usize, usize)) -> RawImage {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
         match self {
-            Camera::Ortho(camera) => camera.render(scene, image_size)
+            Camera::Ortho(camera) => camera.render_hdr_with_context(scene, image_size, context),
+            Camera::Perspective(camera) => {
+                camera.render_hdr_with_context(scene, image_size, context)
+            }
+            Camera::Equirectangular(camera) => {
+                camera.render_hdr_with_context(scene, image_size, context)
+            }
+            Camera::Fisheye(camera) => camera.render_hdr_with_context(scene, image_size, context)
+        }
+    }
+
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        match self {
+            Camera::Ortho(camera) => camera.render_aovs(scene, image_size),
+            Camera::Perspective(camera) => camera.render_aovs(scene, image_size),
+            Camera::Equirectangular(camera) => camera.render_aovs(scene, image_size),
+            Camera::Fisheye(camera) => camera.render_aovs(scene, image_size)
         }
     }
 }
 
 impl Renderer for OrthographicCamera {
-    fn render(&self, scene: &Scene, image_size: (usize, usize)) -> RawImage {
-        let mut output_image = RawImage::new(image_size.0, image_size.1);
-
-        for j in 0..(image_size.1) {
-            let y: f64 = ((-(j as f64) / (image_size.1 as f64)) + 0.5) * self.height;
-            for i in 0..(image_size.0) {
-                let x: f64 = (((i as f64) / (image_size.0 as f64)) - 0.5) * self.width;
-
-                let ray = Ray::new(
-                    Point::new(x, y, 0.0) + self.location,
-                    Point::new(0.0, 0.0, 1.0)
-                );
-
-                // TODO: Optimization for when a triangle is not even in the frame.
-                let mut closest_collision: Option<(Collision, Material)> = None;
-                // Check collision with each object in the scene
-                for obj in &scene.objects {
-                    // Check collision with each triangle in the object
-                    for tri in obj.triangles() {
-                        // Check collision with the triangle
-                        match tri.intersection_point(&ray) {
-                            None => {}
-                            Some(cl) => match &closest_collision {
-                                None => {
-                                    closest_collision = match tri.material() {
-                                        Some(mat) => Some((cl, mat.clone())),
-                                        None => Some((cl, obj.base_material().clone()))
-                                    }
-                                }
-                                Some((closest, _)) => {
-                                    if cl.distance < closest.distance {
-                                        closest_collision = match tri.material() {
-                                            Some(mat) => Some((cl, mat.clone())),
-                                            None => Some((cl, obj.base_material().clone()))
-                                        }
-                                    }
-                                }
-                            }
-                        };
-                    }
-                }
-                match closest_collision {
-                    None => output_image.set_pixel(BLACK, j, i),
-                    Some((_, mat)) => output_image.set_pixel(mat.color().clone(), j, i)
-                }
-            }
-        }
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        let frustum = self.frustum(sample_margin(scene, image_size));
+        render_image(
+            scene,
+            image_size,
+            context,
+            frustum.as_ref(),
+            |x, y, lens| Some(self.lens_ray(x, y, lens))
+        )
+    }
+
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        render_aovs(scene, image_size, self.frustum(0.0).as_ref(), |x, y| {
+            Some(self.primary_ray(x, y))
+        })
+    }
+}
+
+impl Renderer for PerspectiveCamera {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        let frustum = self.frustum(sample_margin(scene, image_size));
+        render_image(
+            scene,
+            image_size,
+            context,
+            frustum.as_ref(),
+            |x, y, lens| Some(self.lens_ray(x, y, lens))
+        )
+    }
+
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        render_aovs(scene, image_size, self.frustum(0.0).as_ref(), |x, y| {
+            Some(self.primary_ray(x, y))
+        })
+    }
+}
+
+impl ProgressiveRenderer for Camera {
+    fn prepare(&self, scene: &Scene, image_size: (usize, usize)) -> RenderGeometry {
+        let frustum = self.frustum(sample_margin(scene, image_size));
+        RenderGeometry::new(scene, frustum.as_ref())
+    }
+
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        render_image_pass(scene, geometry, image_size, pass, |x, y, lens| {
+            self.image_ray(x, y, lens)
+        })
+    }
+}
+
+impl ProgressiveRenderer for OrthographicCamera {
+    fn prepare(&self, scene: &Scene, image_size: (usize, usize)) -> RenderGeometry {
+        let frustum = self.frustum(sample_margin(scene, image_size));
+        RenderGeometry::new(scene, frustum.as_ref())
+    }
+
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        render_image_pass(scene, geometry, image_size, pass, |x, y, lens| {
+            Some(self.lens_ray(x, y, lens))
+        })
+    }
+}
+
+impl ProgressiveRenderer for PerspectiveCamera {
+    fn prepare(&self, scene: &Scene, image_size: (usize, usize)) -> RenderGeometry {
+        let frustum = self.frustum(sample_margin(scene, image_size));
+        RenderGeometry::new(scene, frustum.as_ref())
+    }
+
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        render_image_pass(scene, geometry, image_size, pass, |x, y, lens| {
+            Some(self.lens_ray(x, y, lens))
+        })
+    }
+}
+
+impl Renderer for EquirectangularCamera {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        render_image(scene, image_size, context, None, |x, y, lens| {
+            Some(self.lens_ray(x, y, lens))
+        })
+    }
+
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        render_aovs(scene, image_size, None, |x, y| Some(self.primary_ray(x, y)))
+    }
+}
+
+impl Renderer for FisheyeCamera {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        render_image(scene, image_size, context, None, |x, y, lens| {
+            self.image_ray(x, y, lens)
+        })
+    }
+
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        render_aovs(scene, image_size, None, |x, y| {
+            self.image_ray(x, y, (0.5, 0.5))
+        })
+    }
+}
+
+impl ProgressiveRenderer for EquirectangularCamera {
+    /// Panoramic cameras see too much to cull anything.
+    fn prepare(&self, scene: &Scene, _image_size: (usize, usize)) -> RenderGeometry {
+        RenderGeometry::new(scene, None)
+    }
+
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        render_image_pass(scene, geometry, image_size, pass, |x, y, lens| {
+            Some(self.lens_ray(x, y, lens))
+        })
+    }
+}
+
+impl ProgressiveRenderer for FisheyeCamera {
+    /// Panoramic cameras see too much to cull anything.
+    fn prepare(&self, scene: &Scene, _image_size: (usize, usize)) -> RenderGeometry {
+        RenderGeometry::new(scene, None)
+    }
 
-        output_image
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        render_image_pass(scene, geometry, image_size, pass, |x, y, lens| {
+            self.image_ray(x, y, lens)
+        })
     }
 }
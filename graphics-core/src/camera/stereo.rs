@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests;
+
+use super::{Orientable, PerspectiveCamera, Renderer};
+use crate::images::{Color, RawImage};
+use crate::space::scene::Scene;
+
+/// How the images for the two eyes are put together into one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// The left eye on the left half of the image and the right eye on the
+    /// right half.
+    SideBySide,
+    /// The left eye on the top half of the image and the right eye on the
+    /// bottom half.
+    OverUnder,
+    /// Both eyes in the same image for red/cyan glasses, with the red channel
+    /// from the left eye and the green and blue channels from the right eye.
+    Anaglyph
+}
+
+/// Renders a scene once for each eye from two perspective cameras either side
+/// of a center camera.
+///
+/// The eyes face the same way as the center camera and have their images
+/// shifted sideways so that their views meet at the convergence distance,
+/// rather than being turned in, which would make the corners of the two images
+/// disagree vertically. Things at that distance appear at the depth of the
+/// screen, with anything closer coming out of it and anything further away
+/// going into it. A convergence distance of zero leaves the views parallel, as
+/// if they met infinitely far away.
+#[derive(Debug, Clone)]
+pub struct StereoCamera {
+    camera: PerspectiveCamera,
+    /// How far apart the two eyes are.
+    interocular_distance: f64,
+    /// How far in front of the center camera the eyes' views meet, or zero for
+    /// them to never meet.
+    convergence_distance: f64,
+    layout: StereoLayout
+}
+
+impl StereoCamera {
+    pub fn new(
+        camera: PerspectiveCamera,
+        interocular_distance: f64,
+        convergence_distance: f64,
+        layout: StereoLayout
+    ) -> Self {
+        StereoCamera {
+            camera,
+            interocular_distance,
+            convergence_distance,
+            layout
+        }
+    }
+
+    /// Eyes 6.5cm apart, about the same as most people's, that meet at the
+    /// camera's focus distance and are laid out side by side.
+    pub fn new_default(camera: PerspectiveCamera) -> Self {
+        let convergence_distance = camera.lens().focus_distance();
+        StereoCamera::new(
+            camera,
+            0.065,
+            convergence_distance,
+            StereoLayout::SideBySide
+        )
+    }
+
+    pub fn camera(&self) -> &PerspectiveCamera {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: PerspectiveCamera) {
+        self.camera = camera;
+    }
+
+    pub fn interocular_distance(&self) -> f64 {
+        self.interocular_distance
+    }
+
+    pub fn set_interocular_distance(&mut self, interocular_distance: f64) {
+        self.interocular_distance = interocular_distance;
+    }
+
+    pub fn convergence_distance(&self) -> f64 {
+        self.convergence_distance
+    }
+
+    pub fn set_convergence_distance(&mut self, convergence_distance: f64) {
+        self.convergence_distance = convergence_distance;
+    }
+
+    pub fn layout(&self) -> StereoLayout {
+        self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: StereoLayout) {
+        self.layout = layout;
+    }
+
+    pub fn left_eye(&self) -> PerspectiveCamera {
+        self.eye(-0.5)
+    }
+
+    pub fn right_eye(&self) -> PerspectiveCamera {
+        self.eye(0.5)
+    }
+
+    /// The camera for an eye moved `side` times the interocular distance to
+    /// the right of the center camera.
+    fn eye(&self, side: f64) -> PerspectiveCamera {
+        let mut eye = self.camera.clone();
+        let (right, _, _) = eye.orientation().basis();
+        let offset = side * self.interocular_distance;
+        eye.location = eye.location + right * offset;
+
+        if self.convergence_distance > 0.0 {
+            // Shift the image back towards the center by however much of its
+            // width the offset is at the convergence distance
+            let half_width = (eye.fov() / 2.0).tan() * eye.aspect_ratio();
+            let (shift_x, shift_y) = eye.shift();
+            eye.set_shift((
+                shift_x - offset / (2.0 * self.convergence_distance * half_width),
+                shift_y
+            ));
+        }
+        eye
+    }
+
+    /// Renders both eyes and puts them together using the camera's layout.
+    /// `eye_size` is the size of the image for each eye, so side by side and
+    /// over under images are twice as wide or tall.
+    pub fn render(&self, scene: &Scene, eye_size: (usize, usize)) -> RawImage {
+        let (left, right) = self.render_eyes(scene, eye_size);
+        combine_eyes(&left, &right, self.layout)
+    }
+
+    /// Renders the image for each eye separately, left and then right.
+    pub fn render_eyes(&self, scene: &Scene, eye_size: (usize, usize)) -> (RawImage, RawImage) {
+        (
+            self.left_eye().render(scene, eye_size),
+            self.right_eye().render(scene, eye_size)
+        )
+    }
+}
+
+/// Puts the images for the two eyes, which have to be the same size, together
+/// into one.
+pub fn combine_eyes(left: &RawImage, right: &RawImage, layout: StereoLayout) -> RawImage {
+    let width = left.get_width();
+    let height = left.get_height();
+    assert_eq!(width, right.get_width());
+    assert_eq!(height, right.get_height());
+
+    let (mut image, right_offset) = match layout {
+        StereoLayout::SideBySide => (RawImage::new(width * 2, height), (0, width)),
+        StereoLayout::OverUnder => (RawImage::new(width, height * 2), (height, 0)),
+        StereoLayout::Anaglyph => (RawImage::new(width, height), (0, 0))
+    };
+
+    for row in 0..height {
+        for col in 0..width {
+            let l = &left.get_pixel(row, col).color;
+            let r = &right.get_pixel(row, col).color;
+
+            if layout == StereoLayout::Anaglyph {
+                let color = Color::new(l.r, r.g, r.b, l.a.max(r.a));
+                image.set_pixel(color, row, col);
+            } else {
+                image.set_pixel(l.clone(), row, col);
+                image.set_pixel(r.clone(), row + right_offset.0, col + right_offset.1);
+            }
+        }
+    }
+
+    image
+}
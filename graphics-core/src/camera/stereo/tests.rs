@@ -0,0 +1,157 @@
+use super::*;
+use crate::collision::Ray;
+use crate::images::{BLACK, RED};
+use crate::material::Material;
+use crate::space::Point;
+use crate::test_support::{quad, unlit_scene};
+
+fn colored_image(width: usize, height: usize, color: Color) -> RawImage {
+    let mut image = RawImage::new(width, height);
+    for row in 0..height {
+        for col in 0..width {
+            image.set_pixel(color.clone(), row, col);
+        }
+    }
+
+    image
+}
+
+/// A thin red post straight in front of the camera, `z` away.
+fn scene_with_post(z: f64) -> Scene {
+    let mut scene = unlit_scene();
+    scene.add_object(quad(
+        [
+            Point::new(-0.05, -1.0, z),
+            Point::new(-0.05, 1.0, z),
+            Point::new(0.05, 1.0, z),
+            Point::new(0.05, -1.0, z)
+        ],
+        Material::new(RED)
+    ));
+    scene
+}
+
+/// The average column of the red pixels in the middle row.
+fn post_column(image: &RawImage) -> f64 {
+    let row = image.get_height() / 2;
+    let columns: Vec<usize> = (0..image.get_width())
+        .filter(|&col| image.get_pixel(row, col).color == RED)
+        .collect();
+    assert!(!columns.is_empty());
+
+    columns.iter().sum::<usize>() as f64 / columns.len() as f64
+}
+
+#[test]
+fn eyes_are_apart_and_converge() {
+    let stereo = StereoCamera::new(
+        PerspectiveCamera::new_default(),
+        0.2,
+        5.0,
+        StereoLayout::SideBySide
+    );
+    let left = stereo.left_eye();
+    let right = stereo.right_eye();
+
+    assert_eq!(Point::new(-0.1, 0.0, 0.0), *left.location());
+    assert_eq!(Point::new(0.1, 0.0, 0.0), *right.location());
+
+    let convergence = Point::new(0.0, 0.0, 5.0);
+    for eye in [left, right] {
+        assert_eq!(
+            stereo.camera().orientation().basis(),
+            eye.orientation().basis()
+        );
+
+        let ray = eye.primary_ray(0.5, 0.5);
+        let to_convergence = (convergence - *ray.location()).normalized();
+        assert!((*ray.direction() - to_convergence).length() < 1e-9);
+    }
+}
+
+#[test]
+fn eyes_agree_on_heights_in_the_corners() {
+    let stereo = StereoCamera::new(
+        PerspectiveCamera::new_default(),
+        0.5,
+        2.0,
+        StereoLayout::SideBySide
+    );
+    let (left, right) = (stereo.left_eye(), stereo.right_eye());
+
+    for (x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+        let left_ray = left.primary_ray(x, y);
+        let right_ray = right.primary_ray(x, y);
+        let slope = |ray: &Ray| ray.direction().y / ray.direction().z;
+        assert!((slope(&left_ray) - slope(&right_ray)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn eyes_without_distances_are_the_center_camera() {
+    let stereo = StereoCamera::new(
+        PerspectiveCamera::new_default(),
+        0.0,
+        0.0,
+        StereoLayout::SideBySide
+    );
+
+    for eye in [stereo.left_eye(), stereo.right_eye()] {
+        assert_eq!(stereo.camera().location(), eye.location());
+        assert_eq!((0.0, 0.0), eye.shift());
+    }
+}
+
+#[test]
+fn layouts_place_each_eye() {
+    let left = colored_image(3, 2, RED);
+    let right = colored_image(3, 2, Color::new(0, 255, 255, 255));
+
+    let side_by_side = combine_eyes(&left, &right, StereoLayout::SideBySide);
+    assert_eq!(
+        (6, 2),
+        (side_by_side.get_width(), side_by_side.get_height())
+    );
+    assert_eq!(RED, side_by_side.get_pixel(1, 2).color);
+    assert_eq!(
+        Color::new(0, 255, 255, 255),
+        side_by_side.get_pixel(1, 3).color
+    );
+
+    let over_under = combine_eyes(&left, &right, StereoLayout::OverUnder);
+    assert_eq!((3, 4), (over_under.get_width(), over_under.get_height()));
+    assert_eq!(RED, over_under.get_pixel(1, 0).color);
+    assert_eq!(
+        Color::new(0, 255, 255, 255),
+        over_under.get_pixel(2, 0).color
+    );
+
+    let anaglyph = combine_eyes(&left, &right, StereoLayout::Anaglyph);
+    assert_eq!((3, 2), (anaglyph.get_width(), anaglyph.get_height()));
+    assert_eq!(
+        Color::new(255, 255, 255, 255),
+        anaglyph.get_pixel(0, 0).color
+    );
+}
+
+#[test]
+fn near_objects_shift_apart_between_eyes() {
+    let stereo = StereoCamera::new(
+        PerspectiveCamera::new_default(),
+        0.5,
+        6.0,
+        StereoLayout::SideBySide
+    );
+
+    // At the convergence distance the post is in the same place for both eyes
+    let (left, right) = stereo.render_eyes(&scene_with_post(6.0), (41, 21));
+    assert!((post_column(&left) - post_column(&right)).abs() < 1.0);
+
+    // Nearer than that the left eye sees it further right than the right eye
+    let (left, right) = stereo.render_eyes(&scene_with_post(3.0), (41, 21));
+    assert!(post_column(&left) > post_column(&right) + 1.0);
+
+    let combined = stereo.render(&scene_with_post(3.0), (41, 21));
+    assert_eq!((82, 21), (combined.get_width(), combined.get_height()));
+    assert_eq!(BLACK, combined.get_pixel(0, 0).color);
+}
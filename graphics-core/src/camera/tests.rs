@@ -0,0 +1,443 @@
+use super::*;
+use crate::images::{Color, BLACK, RED};
+use crate::material::Material;
+use crate::render::sampling::Sampling;
+use crate::space::background::Background;
+use crate::space::object::Object;
+use crate::space::ORIGIN;
+use crate::test_support::{count_pixels, quad, unlit_scene};
+
+fn red_square_at_depth(z: f64) -> Object {
+    red_quad([
+        Point::new(-1.0, -1.0, z),
+        Point::new(1.0, -1.0, z),
+        Point::new(1.0, 1.0, z),
+        Point::new(-1.0, 1.0, z)
+    ])
+}
+
+fn red_quad(corners: [Point; 4]) -> Object {
+    quad(corners, Material::new(RED))
+}
+
+#[test]
+fn default_camera_is_perspective() {
+    match Camera::new_default() {
+        Camera::Perspective(_) => {}
+        _ => panic!("the default camera should be a perspective camera")
+    }
+}
+
+#[test]
+fn perspective_center_ray_points_at_target() {
+    let cam = PerspectiveCamera::new(
+        Point::new(1.0, 2.0, 3.0),
+        Point::new(1.0, 2.0, 10.0),
+        Point::new(0.0, 1.0, 0.0),
+        std::f64::consts::FRAC_PI_2,
+        1.0
+    );
+
+    let ray = cam.primary_ray(0.5, 0.5);
+    assert_eq!(Point::new(1.0, 2.0, 3.0), *ray.location());
+    assert_eq!(Point::new(0.0, 0.0, 1.0), *ray.direction());
+}
+
+#[test]
+fn perspective_edge_rays_match_field_of_view() {
+    // With a 90 degree field of view the top edge of the image should be 45
+    // degrees above the view direction, and with an aspect ratio of 2 the
+    // right edge should be twice as far out.
+    let cam = PerspectiveCamera::new(
+        ORIGIN,
+        Point::new(0.0, 0.0, 1.0),
+        Point::new(0.0, 1.0, 0.0),
+        std::f64::consts::FRAC_PI_2,
+        2.0
+    );
+
+    let top = cam.primary_ray(0.5, 0.0);
+    assert_eq!(Point::new(0.0, 1.0, 1.0).normalized(), *top.direction());
+
+    let right = cam.primary_ray(1.0, 0.5);
+    assert_eq!(Point::new(2.0, 0.0, 1.0).normalized(), *right.direction());
+}
+
+#[test]
+fn perspective_shift_moves_the_image_without_turning() {
+    let mut cam = PerspectiveCamera::new(
+        ORIGIN,
+        Point::new(0.0, 0.0, 1.0),
+        Point::new(0.0, 1.0, 0.0),
+        std::f64::consts::FRAC_PI_2,
+        1.0
+    );
+    cam.set_shift((0.5, 0.5));
+
+    // Half the image over, the middle is where the top-right corner was
+    let middle = cam.primary_ray(0.5, 0.5);
+    assert_eq!(Point::new(1.0, 1.0, 1.0).normalized(), *middle.direction());
+    assert_eq!(ORIGIN, *middle.location());
+
+    let frustum = cam.frustum(0.0).unwrap();
+    let point_box = |p: Point| crate::space::bounds::BoundingBox::new(p, p);
+    assert!(frustum.intersects_box(&point_box(Point::new(1.9, 1.9, 1.0))));
+    assert!(!frustum.intersects_box(&point_box(Point::new(-0.1, 1.0, 1.0))));
+}
+
+#[test]
+fn frustums_shifted_past_the_axis_keep_what_is_in_view() {
+    let mut scene = unlit_scene();
+    scene.add_object(red_quad([
+        Point::new(-1.0, 2.0, 4.0),
+        Point::new(1.0, 2.0, 4.0),
+        Point::new(1.0, 4.0, 4.0),
+        Point::new(-1.0, 4.0, 4.0)
+    ]));
+
+    // Shifted far enough up that the whole image is above the camera's axis
+    let mut culled = PerspectiveCamera::new_default();
+    culled.set_shift((0.0, 0.55));
+    let mut unculled = culled.clone();
+    unculled.set_lens(Lens::new(1e-9, 4.0));
+    assert!(culled.frustum(0.0).is_some());
+    assert!(unculled.frustum(0.0).is_none());
+
+    let seen = count_pixels(&unculled.render(&scene, (20, 20)), &RED);
+    assert!(seen > 0);
+    assert_eq!(seen, count_pixels(&culled.render(&scene, (20, 20)), &RED));
+}
+
+#[test]
+fn perspective_objects_shrink_with_distance() {
+    let cam = PerspectiveCamera::new_default();
+
+    let mut near = unlit_scene();
+    near.add_object(red_square_at_depth(4.0));
+    let mut far = unlit_scene();
+    far.add_object(red_square_at_depth(8.0));
+
+    let near_count = count_pixels(&cam.render(&near, (50, 50)), &RED);
+    let far_count = count_pixels(&cam.render(&far, (50, 50)), &RED);
+
+    assert!(far_count > 0);
+    assert!(near_count > far_count * 3);
+}
+
+#[test]
+fn perspective_ignores_objects_behind_camera() {
+    let cam = PerspectiveCamera::new_default();
+    let mut scene = unlit_scene();
+    scene.add_object(red_square_at_depth(-4.0));
+
+    let image = cam.render(&scene, (20, 20));
+    assert_eq!(0, count_pixels(&image, &RED));
+    assert_eq!(400, count_pixels(&image, &BLACK));
+}
+
+#[test]
+fn render_non_square_image() {
+    let cam = PerspectiveCamera::new(
+        ORIGIN,
+        Point::new(0.0, 0.0, 1.0),
+        Point::new(0.0, 1.0, 0.0),
+        std::f64::consts::FRAC_PI_3,
+        2.0
+    );
+    let mut scene = unlit_scene();
+    scene.add_object(red_square_at_depth(5.0));
+
+    let image = cam.render(&scene, (40, 20));
+    assert_eq!(40, image.get_width());
+    assert_eq!(20, image.get_height());
+    assert_eq!(RED, image.get_pixel(10, 20).color);
+    assert_eq!(BLACK, image.get_pixel(0, 0).color);
+}
+
+#[test]
+fn default_orientation_basis() {
+    let (right, up, forward) = Orientation::new_default().basis();
+
+    assert_eq!(Point::new(1.0, 0.0, 0.0), right);
+    assert_eq!(Point::new(0.0, 1.0, 0.0), up);
+    assert_eq!(Point::new(0.0, 0.0, 1.0), forward);
+}
+
+#[test]
+fn orientation_roll_turns_up_towards_right() {
+    let orientation = Orientation::new(
+        Point::new(0.0, 0.0, 1.0),
+        Point::new(0.0, 1.0, 0.0),
+        std::f64::consts::FRAC_PI_2
+    );
+    let (right, up, forward) = orientation.basis();
+
+    assert_eq!(Point::new(0.0, -1.0, 0.0), right);
+    assert_eq!(Point::new(1.0, 0.0, 0.0), up);
+    assert_eq!(Point::new(0.0, 0.0, 1.0), forward);
+}
+
+#[test]
+fn orientation_looking_along_up_vector() {
+    let orientation = Orientation::new(Point::new(0.0, -1.0, 0.0), Point::new(0.0, 1.0, 0.0), 0.0);
+    let (right, up, forward) = orientation.basis();
+
+    assert_eq!(1.0, right.length());
+    assert_eq!(1.0, up.length());
+    assert_eq!(0.0, right.dot(&forward));
+    assert_eq!(0.0, up.dot(&forward));
+}
+
+#[test]
+fn orthographic_top_view() {
+    // Looking straight down from above at a square lying on the ground
+    let mut cam = OrthographicCamera::new(Point::new(0.0, 10.0, 0.0), 4.0, 4.0);
+    cam.set_orientation(Orientation::new(
+        Point::new(0.0, -1.0, 0.0),
+        Point::new(0.0, 0.0, 1.0),
+        0.0
+    ));
+
+    let ray = cam.primary_ray(1.0, 0.0);
+    assert_eq!(Point::new(2.0, 10.0, 2.0), *ray.location());
+    assert_eq!(Point::new(0.0, -1.0, 0.0), *ray.direction());
+
+    let mut scene = unlit_scene();
+    scene.add_object(red_quad([
+        Point::new(-1.0, 0.0, -1.0),
+        Point::new(1.0, 0.0, -1.0),
+        Point::new(1.0, 0.0, 1.0),
+        Point::new(-1.0, 0.0, 1.0)
+    ]));
+
+    let image = cam.render(&scene, (20, 20));
+    assert_eq!(RED, image.get_pixel(10, 10).color);
+    assert_eq!(BLACK, image.get_pixel(0, 0).color);
+}
+
+#[test]
+fn look_at_points_camera_at_target() {
+    // A square standing up in the yz plane
+    let mut scene = unlit_scene();
+    scene.add_object(red_quad([
+        Point::new(0.0, -1.0, 4.0),
+        Point::new(0.0, -1.0, 6.0),
+        Point::new(0.0, 1.0, 6.0),
+        Point::new(0.0, 1.0, 4.0)
+    ]));
+
+    for mut cam in [
+        Camera::Ortho(OrthographicCamera::new(Point::new(5.0, 0.0, 5.0), 3.0, 3.0)),
+        Camera::Perspective(PerspectiveCamera::new(
+            Point::new(5.0, 0.0, 5.0),
+            Point::new(5.0, 0.0, 6.0),
+            Point::new(0.0, 1.0, 0.0),
+            std::f64::consts::FRAC_PI_3,
+            1.0
+        ))
+    ] {
+        // Starting off looking past the square
+        let image = cam.render(&scene, (10, 10));
+        assert_eq!(0, count_pixels(&image, &RED));
+
+        cam.look_at(&Point::new(0.0, 0.0, 5.0));
+        assert_eq!(Point::new(-1.0, 0.0, 0.0), *cam.orientation().direction());
+
+        let image = cam.render(&scene, (10, 10));
+        assert_eq!(RED, image.get_pixel(5, 5).color);
+    }
+}
+
+#[test]
+fn pinhole_lens_ignores_lens_samples() {
+    let cam = PerspectiveCamera::new_default();
+    let center = cam.primary_ray(0.3, 0.8);
+    let corner = cam.lens_ray(0.3, 0.8, (0.0, 1.0));
+
+    assert_eq!(center.location(), corner.location());
+    assert_eq!(center.direction(), corner.direction());
+}
+
+#[test]
+fn lens_rays_meet_at_the_focus_distance() {
+    for mut cam in [
+        Camera::Perspective(PerspectiveCamera::new_default()),
+        Camera::Ortho(OrthographicCamera::new(ORIGIN, 2.0, 2.0))
+    ] {
+        cam.set_lens(Lens::new(0.5, 3.0));
+
+        let center = cam.primary_ray(0.2, 0.7);
+        // Along the view direction this is where the center ray reaches the
+        // focus distance
+        let steps = 3.0 / center.direction().z;
+        let focus_point = *center.location() + *center.direction() * steps;
+
+        for sample in [(0.0, 0.0), (1.0, 0.25), (0.9, 0.9)] {
+            let ray = cam.lens_ray(0.2, 0.7, sample);
+            assert_ne!(center.location(), ray.location());
+            assert!((ray.location().z).abs() < 1e-12);
+            assert!((*ray.location() - *center.location()).length() <= 0.5 + 1e-12);
+
+            let steps = 3.0 / ray.direction().z;
+            let crossing = *ray.location() + *ray.direction() * steps;
+            assert!((crossing - focus_point).length() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn objects_away_from_the_focus_distance_are_blurred() {
+    let mut scene = unlit_scene();
+    scene.add_object(red_square_at_depth(4.0));
+    scene.render_settings.sampling = Sampling::Jittered(4);
+
+    let blurry_pixels = |focus_distance: f64| {
+        let mut cam = PerspectiveCamera::new_default();
+        cam.set_lens(Lens::new(0.2, focus_distance));
+        let image = cam.render(&scene, (40, 40));
+
+        40 * 40 - count_pixels(&image, &RED) - count_pixels(&image, &BLACK)
+    };
+
+    let in_focus = blurry_pixels(4.0);
+    let out_of_focus = blurry_pixels(1.0);
+    assert!(in_focus > 0);
+    assert!(
+        out_of_focus > in_focus * 2,
+        "{} blurry pixels out of focus and {} in focus",
+        out_of_focus,
+        in_focus
+    );
+}
+
+fn assert_direction(expected: Vector, ray: &Ray) {
+    assert!(
+        (*ray.direction() - expected).length() < 1e-9,
+        "expected {:?} but got {:?}",
+        expected,
+        ray.direction()
+    );
+}
+
+#[test]
+fn equirectangular_rays_cover_every_direction() {
+    let cam = EquirectangularCamera::new_default();
+
+    assert_direction(Point::new(0.0, 0.0, 1.0), &cam.primary_ray(0.5, 0.5));
+    assert_direction(Point::new(1.0, 0.0, 0.0), &cam.primary_ray(0.75, 0.5));
+    assert_direction(Point::new(-1.0, 0.0, 0.0), &cam.primary_ray(0.25, 0.5));
+    assert_direction(Point::new(0.0, 0.0, -1.0), &cam.primary_ray(0.0, 0.5));
+    assert_direction(Point::new(0.0, 1.0, 0.0), &cam.primary_ray(0.3, 0.0));
+    assert_direction(Point::new(0.0, -1.0, 0.0), &cam.primary_ray(0.3, 1.0));
+}
+
+#[test]
+fn equirectangular_sees_behind_the_camera() {
+    let mut scene = unlit_scene();
+    scene.add_object(red_square_at_depth(-4.0));
+
+    let image =
+        Camera::Equirectangular(EquirectangularCamera::new_default()).render(&scene, (40, 20));
+
+    // Straight behind the camera is split between the two sides of the image
+    assert_eq!(RED, image.get_pixel(10, 0).color);
+    assert_eq!(RED, image.get_pixel(10, 39).color);
+    assert_eq!(BLACK, image.get_pixel(10, 20).color);
+}
+
+#[test]
+fn fisheye_angle_grows_with_distance_from_the_center() {
+    let cam = FisheyeCamera::new_default();
+
+    assert_direction(Point::new(0.0, 0.0, 1.0), &cam.primary_ray(0.5, 0.5));
+    // The edge of a 180 degree circle is at right angles to the view
+    assert_direction(Point::new(1.0, 0.0, 0.0), &cam.primary_ray(1.0, 0.5));
+    assert_direction(Point::new(0.0, 1.0, 0.0), &cam.primary_ray(0.5, 0.0));
+
+    // Halfway out is halfway there
+    let half = std::f64::consts::FRAC_PI_4;
+    assert_direction(
+        Point::new(-half.sin(), 0.0, half.cos()),
+        &cam.primary_ray(0.25, 0.5)
+    );
+}
+
+#[test]
+fn fisheye_only_renders_inside_the_image_circle() {
+    let mut scene = unlit_scene();
+    scene.background = Background::Solid(Color::new(0, 0, 255, 255));
+    let cam = FisheyeCamera::new_default();
+
+    assert!(cam.covers(0.5, 0.5));
+    assert!(!cam.covers(0.0, 0.0));
+    assert!(Camera::Fisheye(cam.clone())
+        .image_ray(0.02, 0.98, (0.5, 0.5))
+        .is_none());
+
+    let image = cam.render(&scene, (10, 10));
+    assert_eq!(Color::new(0, 0, 255, 255), image.get_pixel(5, 5).color);
+    assert_eq!(0, image.get_pixel(0, 0).color.a);
+    assert_eq!(0, image.get_pixel(9, 9).color.a);
+}
+
+#[test]
+fn panoramic_lenses_focus_on_a_sphere() {
+    let mut cam = EquirectangularCamera::new_default();
+    cam.set_lens(Lens::new(0.5, 3.0));
+
+    for (x, y) in [(0.5, 0.5), (0.1, 0.3), (0.8, 0.9)] {
+        let center = cam.primary_ray(x, y);
+        let focus_point = *center.direction() * 3.0;
+
+        let ray = cam.lens_ray(x, y, (0.9, 0.2));
+        assert_ne!(center.location(), ray.location());
+
+        // The ray has to pass through the focus point
+        let to_focus = focus_point - *ray.location();
+        let along = to_focus.dot(ray.direction());
+        assert!((*ray.direction() * along - to_focus).length() < 1e-9);
+    }
+}
+
+#[test]
+fn perspective_frustum_matches_the_field_of_view() {
+    let cam = PerspectiveCamera::new_default();
+    let frustum = cam.frustum(0.0).unwrap();
+    let point_box = |p: Point| crate::space::bounds::BoundingBox::new(p, p);
+
+    // Rays through the corners of the image are right on the edge
+    let corner = *cam.primary_ray(1.0, 0.0).direction() * 10.0;
+    assert!(frustum.intersects_box(&point_box(corner * 0.99)));
+    assert!(!frustum.intersects_box(&point_box(corner + Point::new(0.1, 0.0, 0.0))));
+    assert!(!frustum.intersects_box(&point_box(Point::new(0.0, 0.0, -1.0))));
+
+    // The margin lets in things just outside the image
+    let wider = cam.frustum(0.1).unwrap();
+    assert!(wider.intersects_box(&point_box(corner + Point::new(0.1, 0.0, 0.0))));
+}
+
+#[test]
+fn orthographic_frustum_is_a_box() {
+    let cam = OrthographicCamera::new(ORIGIN, 2.0, 1.0);
+    let frustum = cam.frustum(0.0).unwrap();
+    let point_box = |p: Point| crate::space::bounds::BoundingBox::new(p, p);
+
+    assert!(frustum.intersects_box(&point_box(Point::new(0.9, 0.4, 100.0))));
+    assert!(!frustum.intersects_box(&point_box(Point::new(1.1, 0.0, 1.0))));
+    assert!(!frustum.intersects_box(&point_box(Point::new(0.0, 0.6, 1.0))));
+    assert!(!frustum.intersects_box(&point_box(Point::new(0.0, 0.0, -0.1))));
+}
+
+#[test]
+fn cameras_that_see_too_much_are_not_culled() {
+    let mut cam = Camera::new_default();
+    assert!(cam.frustum(0.0).is_some());
+
+    cam.set_lens(Lens::new(0.1, 3.0));
+    assert!(cam.frustum(0.0).is_none());
+    assert!(
+        Camera::Equirectangular(EquirectangularCamera::new_default())
+            .frustum(0.0)
+            .is_none()
+    );
+}
@@ -6,18 +6,28 @@
 #[cfg(test)]
 mod tests;
 
+pub mod bvh;
+
 use crate::space::{Line, Point, Triangle};
 
 pub type Ray = Line;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Collision {
     pub point: Point,
-    pub distance: f64
+    pub distance: f64,
+    /// The barycentric coordinates of the point, which are how much of the
+    /// way it is towards the triangle's second and third corners.
+    pub uv: (f64, f64)
 }
 
 impl Collision {
-    pub fn new(point: Point, distance: f64) -> Self {
-        Self { point, distance }
+    pub fn new(point: Point, distance: f64, uv: (f64, f64)) -> Self {
+        Self {
+            point,
+            distance,
+            uv
+        }
     }
 }
 
@@ -55,6 +65,6 @@ impl Collidable for Triangle {
 
         // Because the ray direction is normalized, the distance between the
         // ray's location and the collision point is the same as t.
-        Some(Collision::new(location_of_collision, t))
+        Some(Collision::new(location_of_collision, t, (u, v)))
     }
 }
@@ -0,0 +1,548 @@
+#[cfg(test)]
+mod tests;
+
+use super::{Collidable, Collision, Ray};
+use crate::material::Material;
+use crate::render::stats;
+use crate::space::bounds::BoundingBox;
+use crate::space::frustum::Frustum;
+use crate::space::object::Object;
+use crate::space::scene::Scene;
+use crate::space::{Point, Triangle};
+
+/// Nodes with this many triangles or fewer are not split any further.
+const MAX_LEAF_SIZE: usize = 4;
+/// How many buckets the triangles are sorted into along each axis when looking
+/// for the best place to split a node.
+const SAH_BUCKETS: usize = 12;
+/// The cost of stepping into a node relative to testing a single triangle.
+const TRAVERSAL_COST: f64 = 1.0;
+
+/// A triangle placed in the world, along with where in the scene it came from.
+#[derive(Debug, Clone)]
+pub struct SceneTriangle {
+    pub triangle: Triangle,
+    /// The index of the object in `scene.objects`.
+    pub object: usize,
+    /// The index of the triangle in the object's mesh.
+    pub index: usize,
+    material: Material
+}
+
+impl SceneTriangle {
+    pub fn new(triangle: Triangle, object: usize, index: usize, material: Material) -> Self {
+        SceneTriangle {
+            triangle,
+            object,
+            index,
+            material
+        }
+    }
+
+    /// The material of the triangle, or the material of its object if the
+    /// triangle doesn't have its own.
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Whether the ray is coming at the back of a triangle that is only seen
+    /// from the front.
+    fn is_back_facing(&self, ray: &Ray) -> bool {
+        !self.material.double_sided() && self.triangle.normal().dot(ray.direction()) >= 0.0
+    }
+}
+
+/// How much of a scene was left out of a BVH for being outside the camera's
+/// view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    /// How many objects were entirely outside the view.
+    pub objects_culled: usize,
+    /// How many triangles were left out, including the ones in culled
+    /// objects.
+    pub triangles_culled: usize,
+    pub total_objects: usize,
+    pub total_triangles: usize
+}
+
+/// Where a ray hit the scene, and which triangle it hit.
+pub struct Hit<'a> {
+    pub collision: Collision,
+    pub triangle: &'a SceneTriangle
+}
+
+/// A bounding volume hierarchy over the triangles of a scene, so that a ray
+/// only has to be tested against the few triangles that are actually near it.
+///
+/// The tree is built top down using the surface area heuristic (SAH), which
+/// estimates how expensive each possible split will be to trace rays through
+/// and picks the cheapest one.
+pub struct Bvh {
+    /// The triangles, ordered so that every leaf refers to a continuous range.
+    triangles: Vec<SceneTriangle>,
+    /// All the nodes of the tree. The root is the first one.
+    nodes: Vec<Node>,
+    /// Whether rays ignore the backs of triangles that aren't double sided.
+    backface_culling: bool
+}
+
+struct Node {
+    bounds: BoundingBox,
+    kind: NodeKind
+}
+
+enum NodeKind {
+    Leaf {
+        first: usize,
+        count: usize
+    },
+    Interior {
+        left: usize,
+        right: usize,
+        /// The axis the children were split along. The left child holds the
+        /// triangles with smaller coordinates on this axis.
+        axis: usize
+    }
+}
+
+/// What the builder needs to know about each triangle.
+struct BuildItem {
+    bounds: BoundingBox,
+    centroid: [f64; 3],
+    index: usize
+}
+
+impl Bvh {
+    pub fn new(triangles: Vec<SceneTriangle>) -> Bvh {
+        let mut items: Vec<BuildItem> = triangles
+            .iter()
+            .enumerate()
+            .map(|(index, t)| {
+                let bounds = t.triangle.bounds();
+                BuildItem {
+                    bounds,
+                    centroid: bounds.center().as_arr(),
+                    index
+                }
+            })
+            .collect();
+
+        let mut nodes = Vec::new();
+        if !items.is_empty() {
+            build_node(&mut items, 0, &mut nodes);
+        }
+
+        // Put the triangles in the same order as the build items so each leaf
+        // points at a continuous range of them.
+        let mut slots: Vec<Option<SceneTriangle>> = triangles.into_iter().map(Some).collect();
+        let triangles = items
+            .iter()
+            .map(|item| slots[item.index].take().unwrap())
+            .collect();
+
+        Bvh {
+            triangles,
+            nodes,
+            backface_culling: false
+        }
+    }
+
+    /// Builds the hierarchy out of every triangle of every object in the scene,
+    /// with each object's transform already applied. Back faces are culled if
+    /// the scene's render settings say to.
+    pub fn from_scene(scene: &Scene) -> Bvh {
+        let triangles = world_triangles(scene).into_iter().flatten().collect();
+        Bvh::for_scene(scene, triangles)
+    }
+
+    /// Like [`Bvh::from_scene`], but leaving out everything that is entirely
+    /// outside of the frustum. Objects are checked as a whole using their
+    /// bounds first, then the triangles of the objects that are left are
+    /// checked one at a time.
+    pub fn from_scene_in_view(scene: &Scene, frustum: &Frustum) -> (Bvh, CullingStats) {
+        let objects = world_triangles(scene);
+        let (visible, stats) = cull(&objects, frustum);
+        let visible = visible.into_iter().cloned().collect();
+
+        (Bvh::for_scene(scene, visible), stats)
+    }
+
+    /// Builds both [`Bvh::from_scene`] and [`Bvh::from_scene_in_view`] while
+    /// only placing each object in the world once. The second hierarchy is
+    /// only built when something was actually culled, since otherwise it would
+    /// be the same as the first.
+    pub fn from_scene_with_view(
+        scene: &Scene,
+        frustum: &Frustum
+    ) -> (Bvh, Option<Bvh>, CullingStats) {
+        let objects = world_triangles(scene);
+        let (visible, stats) = cull(&objects, frustum);
+        let visible = if stats.triangles_culled > 0 {
+            let visible = visible.into_iter().cloned().collect();
+            Some(Bvh::for_scene(scene, visible))
+        } else {
+            None
+        };
+        let all = objects.into_iter().flatten().collect();
+
+        (Bvh::for_scene(scene, all), visible, stats)
+    }
+
+    /// A hierarchy over triangles from the scene, following its render
+    /// settings.
+    fn for_scene(scene: &Scene, triangles: Vec<SceneTriangle>) -> Bvh {
+        let mut bvh = Bvh::new(triangles);
+        bvh.set_backface_culling(scene.render_settings.backface_culling);
+        bvh
+    }
+
+    pub fn triangles(&self) -> &[SceneTriangle] {
+        &self.triangles
+    }
+
+    pub fn backface_culling(&self) -> bool {
+        self.backface_culling
+    }
+
+    /// Sets whether rays pass through the backs of triangles whose material
+    /// isn't double sided. This is off for a new hierarchy.
+    pub fn set_backface_culling(&mut self, backface_culling: bool) {
+        self.backface_culling = backface_culling;
+    }
+
+    /// The box around everything in the hierarchy.
+    pub fn bounds(&self) -> BoundingBox {
+        match self.nodes.first() {
+            Some(root) => root.bounds,
+            None => BoundingBox::new_empty()
+        }
+    }
+
+    /// Finds the closest triangle in front of the ray's starting point that the
+    /// ray hits.
+    pub fn closest_hit(&self, ray: &Ray) -> Option<Hit<'_>> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let direction = ray.direction().as_arr();
+        let mut closest: Option<Hit> = None;
+        let mut stack = vec![0];
+        let (mut node_visits, mut triangle_tests) = (0, 0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            node_visits += 1;
+            match node.bounds.ray_entry_distance(ray) {
+                None => continue,
+                Some(entry) => {
+                    // Nothing in this node can be closer than what we have
+                    if let Some(hit) = &closest {
+                        if entry > hit.collision.distance {
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    triangle_tests += count;
+                    for triangle in &self.triangles[first..(first + count)] {
+                        test_triangle(triangle, ray, self.backface_culling, &mut closest);
+                    }
+                }
+                NodeKind::Interior { left, right, axis } => {
+                    // Visit the child nearer the start of the ray first, so
+                    // that the other one can hopefully be skipped.
+                    if direction[axis] > 0.0 {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+            }
+        }
+
+        count_traversal(node_visits, triangle_tests);
+        closest
+    }
+
+    /// Checks whether the ray hits anything before it has gone `max_distance`.
+    /// This stops at the first hit it finds, so it is quicker than looking for
+    /// the closest one when all that matters is if something is in the way.
+    pub fn any_hit(&self, ray: &Ray, max_distance: f64) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = vec![0];
+        let (mut node_visits, mut triangle_tests) = (0, 0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            node_visits += 1;
+            match node.bounds.ray_entry_distance(ray) {
+                Some(entry) if entry < max_distance => {}
+                _ => continue
+            }
+
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for triangle in &self.triangles[first..(first + count)] {
+                        triangle_tests += 1;
+                        if self.backface_culling && triangle.is_back_facing(ray) {
+                            continue;
+                        }
+                        if let Some(cl) = triangle.triangle.intersection_point(ray) {
+                            if cl.distance >= 0.0 && cl.distance < max_distance {
+                                count_traversal(node_visits, triangle_tests);
+                                return true;
+                            }
+                        }
+                    }
+                }
+                NodeKind::Interior { left, right, .. } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        count_traversal(node_visits, triangle_tests);
+        false
+    }
+
+    /// Does the same thing as [`Bvh::closest_hit`] by testing the ray against
+    /// every triangle without using the hierarchy. This is very slow and mostly
+    /// useful to check that the hierarchy gives the right answer.
+    pub fn closest_hit_brute_force(&self, ray: &Ray) -> Option<Hit<'_>> {
+        let mut closest: Option<Hit> = None;
+        for triangle in &self.triangles {
+            test_triangle(triangle, ray, self.backface_culling, &mut closest);
+        }
+        count_traversal(0, self.triangles.len());
+
+        closest
+    }
+}
+
+/// The triangles of every object placed in the world, grouped by object.
+fn world_triangles(scene: &Scene) -> Vec<Vec<SceneTriangle>> {
+    scene
+        .objects
+        .iter()
+        .enumerate()
+        .map(|(object_index, obj)| {
+            obj.triangles()
+                .into_iter()
+                .enumerate()
+                .map(|(index, triangle)| scene_triangle(obj, object_index, index, triangle))
+                .collect()
+        })
+        .collect()
+}
+
+/// Picks out the triangles that might be inside the frustum, checking each
+/// object's bounds before its triangles.
+fn cull<'a>(
+    objects: &'a [Vec<SceneTriangle>],
+    frustum: &Frustum
+) -> (Vec<&'a SceneTriangle>, CullingStats) {
+    let mut stats = CullingStats {
+        total_objects: objects.len(),
+        ..CullingStats::default()
+    };
+
+    let mut visible = Vec::new();
+    for triangles in objects {
+        stats.total_triangles += triangles.len();
+
+        // The triangles are already in the world, so this is the same as the
+        // object's bounds without transforming it again
+        let bounds = BoundingBox::around_triangles(triangles.iter().map(|t| &t.triangle));
+        if !frustum.intersects_box(&bounds) {
+            stats.objects_culled += 1;
+            stats.triangles_culled += triangles.len();
+            continue;
+        }
+
+        for triangle in triangles {
+            if frustum.intersects_triangle(&triangle.triangle) {
+                visible.push(triangle);
+            } else {
+                stats.triangles_culled += 1;
+            }
+        }
+    }
+
+    (visible, stats)
+}
+
+/// Places a triangle of an object in the scene, giving it the object's
+/// material if it doesn't have its own.
+fn scene_triangle(
+    obj: &Object,
+    object_index: usize,
+    index: usize,
+    triangle: Triangle
+) -> SceneTriangle {
+    let material = match triangle.material() {
+        Some(mat) => mat.clone(),
+        None => obj.base_material().clone()
+    };
+
+    SceneTriangle::new(triangle, object_index, index, material)
+}
+
+/// Adds the work done by one query to the render stats.
+fn count_traversal(node_visits: usize, triangle_tests: usize) {
+    stats::count(|counts| {
+        counts.bvh_node_visits += node_visits as u64;
+        counts.triangle_tests += triangle_tests as u64;
+    });
+}
+
+/// Replaces `closest` with the hit on this triangle if the ray hits it and the
+/// hit is closer than what was already there.
+fn test_triangle<'a>(
+    triangle: &'a SceneTriangle,
+    ray: &Ray,
+    backface_culling: bool,
+    closest: &mut Option<Hit<'a>>
+) {
+    if backface_culling && triangle.is_back_facing(ray) {
+        return;
+    }
+
+    let collision = match triangle.triangle.intersection_point(ray) {
+        // Anything behind the start of the ray can't be seen
+        Some(cl) if cl.distance >= 0.0 => cl,
+        _ => return
+    };
+
+    let is_closer = match closest {
+        None => true,
+        Some(hit) => collision.distance < hit.collision.distance
+    };
+    if is_closer {
+        *closest = Some(Hit {
+            collision,
+            triangle
+        });
+    }
+}
+
+/// Builds the node for the given items and everything below it, returning the
+/// index of the new node. `offset` is where the items start in the final order
+/// of triangles.
+fn build_node(items: &mut [BuildItem], offset: usize, nodes: &mut Vec<Node>) -> usize {
+    let mut bounds = BoundingBox::new_empty();
+    for item in items.iter() {
+        bounds = bounds.union(&item.bounds);
+    }
+
+    let node_index = nodes.len();
+    let leaf = NodeKind::Leaf {
+        first: offset,
+        count: items.len()
+    };
+    nodes.push(Node { bounds, kind: leaf });
+
+    if items.len() <= MAX_LEAF_SIZE {
+        return node_index;
+    }
+
+    let (axis, split_bucket, centroid_bounds) = match find_split(items, &bounds) {
+        Some(split) => split,
+        None => return node_index
+    };
+
+    // Move everything that goes in the left child to the front
+    let mut left_count = 0;
+    for i in 0..items.len() {
+        if bucket_index(&items[i], axis, &centroid_bounds) < split_bucket {
+            items.swap(i, left_count);
+            left_count += 1;
+        }
+    }
+
+    let (left_items, right_items) = items.split_at_mut(left_count);
+    let left = build_node(left_items, offset, nodes);
+    let right = build_node(right_items, offset + left_count, nodes);
+    nodes[node_index].kind = NodeKind::Interior { left, right, axis };
+
+    node_index
+}
+
+/// Uses the surface area heuristic to find the cheapest way to split the items
+/// in two. Returns the axis and the first bucket of the right side, along with
+/// the bounds of the item centroids the buckets were made from. If keeping all
+/// the items together is cheaper than any split, this returns `None`.
+fn find_split(items: &[BuildItem], bounds: &BoundingBox) -> Option<(usize, usize, BoundingBox)> {
+    let mut centroid_bounds = BoundingBox::new_empty();
+    for item in items {
+        let c = item.centroid;
+        centroid_bounds.include_point(&Point::new(c[0], c[1], c[2]));
+    }
+
+    // Costs are all multiplied by the parent's surface area to avoid dividing
+    // by zero for flat nodes.
+    let mut best_cost = items.len() as f64 * bounds.surface_area();
+    let mut best = None;
+
+    for axis in 0..3 {
+        let extent = centroid_bounds.size().as_arr()[axis];
+        if extent <= 0.0 {
+            continue;
+        }
+
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut bucket_bounds = [BoundingBox::new_empty(); SAH_BUCKETS];
+        for item in items {
+            let b = bucket_index(item, axis, &centroid_bounds);
+            counts[b] += 1;
+            bucket_bounds[b] = bucket_bounds[b].union(&item.bounds);
+        }
+
+        for split in 1..SAH_BUCKETS {
+            let (left_bounds, left_count) =
+                merge_buckets(&bucket_bounds[..split], &counts[..split]);
+            let (right_bounds, right_count) =
+                merge_buckets(&bucket_bounds[split..], &counts[split..]);
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+
+            let cost = TRAVERSAL_COST * bounds.surface_area()
+                + left_count as f64 * left_bounds.surface_area()
+                + right_count as f64 * right_bounds.surface_area();
+            if cost < best_cost {
+                best_cost = cost;
+                best = Some((axis, split));
+            }
+        }
+    }
+
+    best.map(|(axis, split)| (axis, split, centroid_bounds))
+}
+
+/// Combines a range of buckets into their total bounds and item count.
+fn merge_buckets(bounds: &[BoundingBox], counts: &[usize]) -> (BoundingBox, usize) {
+    let mut total_bounds = BoundingBox::new_empty();
+    for b in bounds {
+        total_bounds = total_bounds.union(b);
+    }
+
+    (total_bounds, counts.iter().sum())
+}
+
+/// Which of the SAH buckets along the axis the item's centroid falls into.
+fn bucket_index(item: &BuildItem, axis: usize, centroid_bounds: &BoundingBox) -> usize {
+    let min = centroid_bounds.min.as_arr()[axis];
+    let extent = centroid_bounds.size().as_arr()[axis];
+    let relative = (item.centroid[axis] - min) / extent;
+
+    ((relative * SAH_BUCKETS as f64) as usize).min(SAH_BUCKETS - 1)
+}
@@ -0,0 +1,268 @@
+use super::*;
+use crate::images::Color;
+use crate::space::object::{Mesh, Object};
+use crate::space::transform::TransformStep;
+use crate::space::{Vector, ORIGIN};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn random_point(rng: &mut StdRng, scale: f64) -> Point {
+    Point::new(
+        rng.gen_range(-scale..scale),
+        rng.gen_range(-scale..scale),
+        rng.gen_range(-scale..scale)
+    )
+}
+
+fn random_triangle_soup(rng: &mut StdRng, count: usize) -> Vec<SceneTriangle> {
+    let mut triangles = Vec::new();
+    for index in 0..count {
+        let center = random_point(rng, 10.0);
+        let triangle = Triangle::new([
+            center + random_point(rng, 1.0),
+            center + random_point(rng, 1.0),
+            center + random_point(rng, 1.0)
+        ]);
+        triangles.push(SceneTriangle::new(triangle, 0, index, Material::default()));
+    }
+
+    triangles
+}
+
+#[test]
+fn empty_bvh_never_hits() {
+    let bvh = Bvh::new(Vec::new());
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    assert!(bvh.closest_hit(&ray).is_none());
+    assert!(bvh.bounds().is_empty());
+}
+
+#[test]
+fn bvh_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let bvh = Bvh::new(random_triangle_soup(&mut rng, 2000));
+    assert_eq!(2000, bvh.triangles().len());
+
+    let mut hits = 0;
+    for _ in 0..2000 {
+        let origin = random_point(&mut rng, 15.0);
+        let direction: Vector = random_point(&mut rng, 1.0);
+        let ray = Ray::new(origin, direction);
+
+        let fast = bvh.closest_hit(&ray);
+        let slow = bvh.closest_hit_brute_force(&ray);
+        match (fast, slow) {
+            (None, None) => {}
+            (Some(fast), Some(slow)) => {
+                hits += 1;
+                assert_eq!(slow.collision.distance, fast.collision.distance);
+                assert_eq!(slow.collision.point, fast.collision.point);
+            }
+            _ => panic!("the bvh and brute force disagree on whether there is a hit")
+        }
+    }
+
+    // Make sure the test is actually testing something
+    assert!(hits > 100);
+}
+
+#[test]
+fn bvh_ignores_hits_behind_the_ray() {
+    let triangle = Triangle::new([
+        Point::new(0.0, 1.0, -1.0),
+        Point::new(1.0, -1.0, -1.0),
+        Point::new(-1.0, -1.0, -1.0)
+    ]);
+    let bvh = Bvh::new(vec![SceneTriangle::new(
+        triangle,
+        0,
+        0,
+        Material::default()
+    )]);
+
+    assert!(bvh
+        .closest_hit(&Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0)))
+        .is_none());
+    assert!(bvh
+        .closest_hit(&Ray::new(ORIGIN, Point::new(0.0, 0.0, -1.0)))
+        .is_some());
+}
+
+#[test]
+fn bvh_from_scene_keeps_track_of_objects() {
+    let mut scene = Scene::new_empty();
+    for i in 0..3 {
+        let mut triangle = Triangle::new([
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(1.0, -1.0, 0.0),
+            Point::new(-1.0, -1.0, 0.0)
+        ]);
+        triangle.set_material(Material::new(Color::new(i, 0, 0, 255)));
+        let mut obj = Object::new(Mesh::new(vec![triangle]));
+        obj.transform.add_step(TransformStep::Translate(Point::new(
+            0.0,
+            0.0,
+            5.0 + i as f64
+        )));
+        scene.add_object(obj);
+    }
+    let bvh = Bvh::from_scene(&scene);
+
+    let hit = bvh
+        .closest_hit(&Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0)))
+        .unwrap();
+    assert_eq!(5.0, hit.collision.distance);
+    assert_eq!(0, hit.triangle.object);
+    assert_eq!(0, hit.triangle.index);
+
+    let hit = bvh
+        .closest_hit(&Ray::new(
+            Point::new(0.0, 0.0, 6.5),
+            Point::new(0.0, 0.0, 1.0)
+        ))
+        .unwrap();
+    assert_eq!(2, hit.triangle.object);
+    assert_eq!(&Color::new(2, 0, 0, 255), hit.triangle.material().color());
+}
+
+#[test]
+fn any_hit_respects_max_distance() {
+    let triangle = Triangle::new([
+        Point::new(0.0, 1.0, 3.0),
+        Point::new(1.0, -1.0, 3.0),
+        Point::new(-1.0, -1.0, 3.0)
+    ]);
+    let bvh = Bvh::new(vec![SceneTriangle::new(
+        triangle,
+        0,
+        0,
+        Material::default()
+    )]);
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    assert!(bvh.any_hit(&ray, 4.0));
+    assert!(bvh.any_hit(&ray, f64::INFINITY));
+    assert!(!bvh.any_hit(&ray, 2.0));
+    assert!(!bvh.any_hit(&Ray::new(ORIGIN, Point::new(0.0, 0.0, -1.0)), 10.0));
+}
+
+#[test]
+fn any_hit_matches_closest_hit() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let bvh = Bvh::new(random_triangle_soup(&mut rng, 500));
+
+    for _ in 0..500 {
+        let ray = Ray::new(random_point(&mut rng, 15.0), random_point(&mut rng, 1.0));
+        let max_distance = rng.gen_range(0.0..30.0);
+
+        let expected = match bvh.closest_hit(&ray) {
+            Some(hit) => hit.collision.distance < max_distance,
+            None => false
+        };
+        assert_eq!(expected, bvh.any_hit(&ray, max_distance));
+    }
+}
+
+#[test]
+fn culling_leaves_out_what_is_outside_the_view() {
+    use crate::space::frustum::{Frustum, Plane};
+
+    let triangle_at = |x: f64, z: f64| {
+        Triangle::new([
+            Point::new(x, 1.0, z),
+            Point::new(x + 1.0, -1.0, z),
+            Point::new(x - 1.0, -1.0, z)
+        ])
+    };
+
+    let mut scene = Scene::new_empty();
+    // Entirely in front of the view
+    scene.add_object(Object::new(Mesh::new(vec![triangle_at(0.0, 5.0)])));
+    // Entirely behind it
+    scene.add_object(Object::new(Mesh::new(vec![
+        triangle_at(0.0, -5.0),
+        triangle_at(2.0, -5.0),
+    ])));
+    // One triangle on each side
+    scene.add_object(Object::new(Mesh::new(vec![
+        triangle_at(0.0, 3.0),
+        triangle_at(0.0, -3.0),
+    ])));
+
+    let frustum = Frustum::new(vec![Plane::new(ORIGIN, Point::new(0.0, 0.0, 1.0))]);
+    let (bvh, stats) = Bvh::from_scene_in_view(&scene, &frustum);
+
+    assert_eq!(
+        CullingStats {
+            objects_culled: 1,
+            triangles_culled: 3,
+            total_objects: 3,
+            total_triangles: 5
+        },
+        stats
+    );
+    assert_eq!(2, bvh.triangles().len());
+    // Objects keep their place in the scene
+    assert!(bvh
+        .triangles()
+        .iter()
+        .any(|t| t.object == 2 && t.index == 0));
+}
+
+#[test]
+fn a_view_hierarchy_is_only_built_when_something_is_culled() {
+    use crate::space::frustum::{Frustum, Plane};
+
+    let mut scene = Scene::new_empty();
+    scene.add_object(Object::new(Mesh::new(vec![Triangle::new([
+        Point::new(0.0, 1.0, 5.0),
+        Point::new(1.0, -1.0, 5.0),
+        Point::new(-1.0, -1.0, 5.0)
+    ])])));
+
+    let in_front = Frustum::new(vec![Plane::new(ORIGIN, Point::new(0.0, 0.0, 1.0))]);
+    let (all, visible, stats) = Bvh::from_scene_with_view(&scene, &in_front);
+    assert_eq!(1, all.triangles().len());
+    assert!(visible.is_none());
+    assert_eq!(0, stats.triangles_culled);
+
+    let behind = Frustum::new(vec![Plane::new(ORIGIN, Point::new(0.0, 0.0, -1.0))]);
+    let (all, visible, stats) = Bvh::from_scene_with_view(&scene, &behind);
+    assert_eq!(1, all.triangles().len());
+    assert_eq!(0, visible.unwrap().triangles().len());
+    assert_eq!(1, stats.triangles_culled);
+}
+
+#[test]
+fn back_faces_are_only_culled_for_one_sided_materials() {
+    // The normal points along -z
+    let triangle = Triangle::new([
+        Point::new(0.0, 1.0, -1.0),
+        Point::new(1.0, -1.0, -1.0),
+        Point::new(-1.0, -1.0, -1.0)
+    ]);
+    let mut one_sided = Material::default();
+    one_sided.set_double_sided(false);
+    let front = Ray::new(Point::new(0.0, 0.0, -2.0), Point::new(0.0, 0.0, 1.0));
+    let back = Ray::new(ORIGIN, Point::new(0.0, 0.0, -1.0));
+
+    let mut bvh = Bvh::new(vec![SceneTriangle::new(triangle.clone(), 0, 0, one_sided)]);
+    assert!(bvh.closest_hit(&back).is_some());
+
+    bvh.set_backface_culling(true);
+    assert!(bvh.closest_hit(&front).is_some());
+    assert!(bvh.any_hit(&front, 10.0));
+    assert!(bvh.closest_hit(&back).is_none());
+    assert!(bvh.closest_hit_brute_force(&back).is_none());
+    assert!(!bvh.any_hit(&back, 10.0));
+
+    let mut bvh = Bvh::new(vec![SceneTriangle::new(
+        triangle,
+        0,
+        0,
+        Material::default()
+    )]);
+    bvh.set_backface_culling(true);
+    assert!(bvh.closest_hit(&back).is_some());
+}
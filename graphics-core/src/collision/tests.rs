@@ -17,7 +17,9 @@ fn basic_collision_test() {
 
     match triangle.intersection_point(&ray) {
         None => panic!("Ray should intersect"),
-        Some(Collision { point, distance }) => {
+        Some(Collision {
+            point, distance, ..
+        }) => {
             assert_eq!(Point::new(0.0, 0.0, 1.0), point);
             assert_eq!(1.0, distance);
         }
@@ -31,7 +33,9 @@ fn ray_backwards_into_triangle() {
 
     match triangle.intersection_point(&ray) {
         None => panic!("Ray should not miss"),
-        Some(Collision { point, distance }) => {
+        Some(Collision {
+            point, distance, ..
+        }) => {
             assert_eq!(Point::new(0.0, 0.0, 1.0), point);
             assert_eq!(-1.0, distance);
         }
@@ -45,7 +49,9 @@ fn test_ray_collision_on_an_angle() {
 
     match triangle.intersection_point(&ray) {
         None => panic!("Ray should intersect"),
-        Some(Collision { point, distance }) => {
+        Some(Collision {
+            point, distance, ..
+        }) => {
             assert_eq!(Point::new(0.0, 0.0, 1.0), point);
             println!("{}", distance);
             assert!((distance - 3.0f64.sqrt()).abs() < 0.00001);
@@ -53,6 +59,21 @@ fn test_ray_collision_on_an_angle() {
     };
 }
 
+#[test]
+fn collisions_know_where_they_are_on_the_triangle() {
+    let triangle = create_test_triangle();
+    let uv_towards = |target: Point| {
+        let ray = Ray::new(ORIGIN, target);
+        triangle.intersection_point(&ray).unwrap().uv
+    };
+
+    let (u, v) = uv_towards(Point::new(0.0, -1.0, 1.0));
+    assert!((u - 0.5).abs() < 1e-9 && (v - 0.5).abs() < 1e-9);
+
+    let (u, v) = uv_towards(Point::new(0.5, 0.0, 1.0));
+    assert!((u - 0.5).abs() < 1e-9 && v.abs() < 1e-9);
+}
+
 #[test]
 fn test_array_of_rays() {
     let mut triangle = create_test_triangle();
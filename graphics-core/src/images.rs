@@ -1,12 +1,16 @@
 #[cfg(test)]
 mod tests;
 
-use png::Encoder;
+pub mod tone_mapping;
+
+use png::{Decoder, Encoder};
 use rand;
 use std::fmt::{Debug, Error as FormatterError, Formatter};
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::{Add, AddAssign, Mul};
 use std::path::Path;
+use tone_mapping::ToneMapping;
 use uuid::Uuid;
 
 pub const WHITE: Color = Color {
@@ -88,6 +92,92 @@ impl Debug for Color {
     }
 }
 
+/// A colour where each channel is a float. Zero is none of a channel and one is
+/// the most that fits in a [`Color`], but values are allowed to go outside of
+/// that while doing lighting math.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatColor {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64
+}
+
+impl FloatColor {
+    pub fn new(r: f64, g: f64, b: f64, a: f64) -> FloatColor {
+        FloatColor { r, g, b, a }
+    }
+
+    pub fn from_color(color: &Color) -> FloatColor {
+        FloatColor {
+            r: color.r as f64 / 255.0,
+            g: color.g as f64 / 255.0,
+            b: color.b as f64 / 255.0,
+            a: color.a as f64 / 255.0
+        }
+    }
+
+    /// Converts back to a regular colour, clipping anything outside of the
+    /// zero to one range.
+    pub fn to_color(&self) -> Color {
+        let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        Color::new(
+            channel(self.r),
+            channel(self.g),
+            channel(self.b),
+            channel(self.a)
+        )
+    }
+}
+
+impl Add for FloatColor {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        FloatColor::new(
+            self.r + other.r,
+            self.g + other.g,
+            self.b + other.b,
+            self.a + other.a
+        )
+    }
+}
+
+impl AddAssign for FloatColor {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Mul for FloatColor {
+    type Output = Self;
+
+    /// Multiplies each channel by the same channel of the other colour, like
+    /// when light of one colour reflects off a surface of another.
+    fn mul(self, other: Self) -> Self {
+        FloatColor::new(
+            self.r * other.r,
+            self.g * other.g,
+            self.b * other.b,
+            self.a * other.a
+        )
+    }
+}
+
+impl Mul<f64> for FloatColor {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self {
+        FloatColor::new(
+            self.r * other,
+            self.g * other,
+            self.b * other,
+            self.a * other
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Pixel {
     pub color: Color
@@ -106,11 +196,11 @@ pub struct RawImage {
 impl RawImage {
     pub fn new(width: usize, height: usize) -> Self {
         let mut pixels: Vec<Vec<Pixel>> = Vec::new();
-        pixels.reserve(width);
+        pixels.reserve(height);
 
-        for _ in 0..width {
-            let column: Vec<Pixel> = vec![Pixel::new(CLEAR); height];
-            pixels.push(column);
+        for _ in 0..height {
+            let row: Vec<Pixel> = vec![Pixel::new(CLEAR); width];
+            pixels.push(row);
         }
 
         RawImage { pixels }
@@ -125,7 +215,8 @@ impl RawImage {
     }
 
     pub fn get_width(&self) -> usize {
-        self.pixels[0].len()
+        // An image without any rows has nothing to measure the width from
+        self.pixels.first().map_or(0, |row| row.len())
     }
 
     pub fn get_height(&self) -> usize {
@@ -161,6 +252,37 @@ impl RawImage {
         data
     }
 
+    /// Reads a PNG file. Images with fewer channels or other bit depths are
+    /// converted to 8-bit RGBA, with alpha being opaque if the file has none.
+    pub fn load_from_path(path: &str) -> Result<RawImage, String> {
+        let file = File::open(Path::new(path)).map_err(|e| format!("{}", e))?;
+        let mut decoder = Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| format!("{}", e))?;
+
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).map_err(|e| format!("{}", e))?;
+
+        let channels = info.color_type.samples();
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut image = RawImage::new(width, height);
+        for row in 0..height {
+            for col in 0..width {
+                let start = row * info.line_size + col * channels;
+                let p = &data[start..(start + channels)];
+                let color = match channels {
+                    1 => Color::new(p[0], p[0], p[0], 255),
+                    2 => Color::new(p[0], p[0], p[0], p[1]),
+                    3 => Color::new(p[0], p[1], p[2], 255),
+                    _ => Color::new(p[0], p[1], p[2], p[3])
+                };
+                image.set_pixel(color, row, col);
+            }
+        }
+
+        Ok(image)
+    }
+
     pub fn save_image_to_path(&self, path: &str) -> Result<(), String> {
         let path = Path::new(path);
         let file = File::create(path).map_err(|e| format!("{}", e))?;
@@ -180,6 +302,119 @@ impl RawImage {
     }
 }
 
+/// A high dynamic range image where every pixel is a [`FloatColor`], so
+/// colours brighter than white aren't lost. Turn it into a [`RawImage`] with
+/// [`FloatImage::to_raw_image`] to display or save it as a PNG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<FloatColor>
+}
+
+impl FloatImage {
+    /// An image where every pixel is transparent black.
+    pub fn new(width: usize, height: usize) -> Self {
+        FloatImage {
+            width,
+            height,
+            pixels: vec![FloatColor::new(0.0, 0.0, 0.0, 0.0); width * height]
+        }
+    }
+
+    /// Makes an image out of pixels given row by row, starting from the
+    /// top-left corner.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<FloatColor>) -> Self {
+        assert_eq!(width * height, pixels.len());
+
+        FloatImage {
+            width,
+            height,
+            pixels
+        }
+    }
+
+    pub fn get_pixel(&self, row: usize, col: usize) -> &FloatColor {
+        &self.pixels[row * self.width + col]
+    }
+
+    pub fn set_pixel(&mut self, c: FloatColor, row: usize, col: usize) {
+        self.pixels[row * self.width + col] = c;
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Tone maps every pixel down into an 8-bit image.
+    pub fn to_raw_image(&self, tone_mapping: &ToneMapping) -> RawImage {
+        let mut image = RawImage::new(self.width, self.height);
+        for (index, pixel) in self.pixels.iter().enumerate() {
+            image.set_pixel(
+                tone_mapping.map(pixel),
+                index / self.width,
+                index % self.width
+            );
+        }
+
+        image
+    }
+
+    /// Saves the red, green, and blue channels without any tone mapping to a
+    /// PFM file.
+    pub fn save_pfm_to_path(&self, path: &str) -> Result<(), String> {
+        let values: Vec<[f32; 3]> = self
+            .pixels
+            .iter()
+            .map(|p| [p.r as f32, p.g as f32, p.b as f32])
+            .collect();
+
+        save_pfm_to_path(path, self.width, self.height, &values)
+    }
+}
+
+/// Saves floating point RGB values to a PFM (portable float map) file, which
+/// keeps the exact values rather than squashing them into 0 to 255. Pixels are
+/// given row by row starting from the top-left corner.
+pub fn save_pfm_to_path(
+    path: &str,
+    width: usize,
+    height: usize,
+    pixels: &[[f32; 3]]
+) -> Result<(), String> {
+    if pixels.len() != width * height {
+        return Err(format!(
+            "expected {} pixels for a {}x{} image but got {}",
+            width * height,
+            width,
+            height,
+            pixels.len()
+        ));
+    }
+
+    let file = File::create(Path::new(path)).map_err(|e| format!("{}", e))?;
+    let mut w = BufWriter::new(file);
+
+    // A negative scale means the floats are little endian
+    write!(w, "PF\n{} {}\n-1.0\n", width, height).map_err(|e| format!("{}", e))?;
+    // PFM files store the bottom row first
+    for row in (0..height).rev() {
+        for pixel in &pixels[(row * width)..((row + 1) * width)] {
+            for channel in pixel {
+                w.write_all(&channel.to_le_bytes())
+                    .map_err(|e| format!("{}", e))?;
+            }
+        }
+    }
+    w.flush().map_err(|e| format!("{}", e))?;
+
+    Ok(())
+}
+
 fn generate_random_temp_path() -> String {
     let uuid = Uuid::new_v4();
     format!("./tmp-yapre/temp_image_{}.png", uuid)
@@ -51,3 +51,91 @@ fn test_random_color() {
     // the chances that all three colours are equal is very low
     assert!(!(a == b && b == c));
 }
+
+#[test]
+fn test_non_square_image_dimensions() {
+    let mut image = RawImage::new(40, 10);
+
+    assert_eq!(40, image.get_width());
+    assert_eq!(10, image.get_height());
+
+    // The bottom-right pixel should be addressable as (row, col)
+    image.set_pixel(RED, 9, 39);
+    assert_eq!(RED, image.get_pixel(9, 39).color);
+}
+
+#[test]
+fn test_float_color_round_trip() {
+    let color = Color::new(0, 51, 204, 255);
+    let float_color = FloatColor::from_color(&color);
+
+    assert_eq!(FloatColor::new(0.0, 0.2, 0.8, 1.0), float_color);
+    assert_eq!(color, float_color.to_color());
+}
+
+#[test]
+fn test_float_color_clips_when_converted() {
+    let color = FloatColor::new(1.5, -0.5, 0.5, 1.0).to_color();
+
+    assert_eq!(Color::new(255, 0, 128, 255), color);
+}
+
+#[test]
+fn test_float_color_math() {
+    let a = FloatColor::new(0.5, 0.25, 1.0, 1.0);
+    let b = FloatColor::new(0.5, 1.0, 0.0, 1.0);
+
+    assert_eq!(FloatColor::new(1.0, 1.25, 1.0, 2.0), a + b);
+    assert_eq!(FloatColor::new(0.25, 0.25, 0.0, 1.0), a * b);
+    assert_eq!(FloatColor::new(1.0, 0.5, 2.0, 2.0), a * 2.0);
+}
+
+#[test]
+fn test_save_pfm() {
+    let path = generate_random_temp_path().replace(".png", ".pfm");
+    std::fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+
+    let pixels = [[1.0, 2.0, 3.0], [0.5, 0.0, -1.0]];
+    save_pfm_to_path(&path, 1, 2, &pixels).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let header = b"PF\n1 2\n-1.0\n";
+    assert_eq!(header, &data[..header.len()]);
+    assert_eq!(header.len() + 2 * 3 * 4, data.len());
+    // The bottom row comes first
+    let first = f32::from_le_bytes(data[header.len()..(header.len() + 4)].try_into().unwrap());
+    assert_eq!(0.5, first);
+
+    assert!(save_pfm_to_path(&path, 2, 2, &pixels).is_err());
+}
+
+#[test]
+fn test_float_image_to_raw_image() {
+    let mut image = FloatImage::new(2, 1);
+    assert_eq!(&FloatColor::new(0.0, 0.0, 0.0, 0.0), image.get_pixel(0, 1));
+
+    image.set_pixel(FloatColor::new(3.0, 0.5, 0.0, 1.0), 0, 1);
+    let raw = image.to_raw_image(&ToneMapping::new_default());
+
+    assert_eq!(2, raw.get_width());
+    assert_eq!(Color::new(0, 0, 0, 0), raw.get_pixel(0, 0).color);
+    assert_eq!(Color::new(255, 128, 0, 255), raw.get_pixel(0, 1).color);
+}
+
+#[test]
+fn test_load_image() {
+    let mut image = RawImage::new(3, 2);
+    image.set_pixel(Color::new(10, 20, 30, 40), 1, 2);
+    image.set_pixel(WHITE, 0, 0);
+    let path = generate_random_temp_path();
+    std::fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+
+    image.save_image_to_path(&path).unwrap();
+    let loaded = RawImage::load_from_path(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!(image.get_image_data(), loaded.get_image_data());
+    assert!(RawImage::load_from_path(&path).is_err());
+}
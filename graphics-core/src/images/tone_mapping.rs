@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests;
+
+use super::{Color, FloatColor};
+
+/// How colours brighter than a [`Color`] can hold get squeezed down into range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapOperator {
+    /// Anything too bright is cut off at white.
+    Clamp,
+    /// Reinhard's operator, `c / (1 + c)`, which leaves dark colours almost
+    /// alone and smoothly rolls off bright ones so they never quite reach
+    /// white.
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve, which gives more
+    /// contrast than Reinhard and lets bright colours reach white.
+    Aces
+}
+
+impl ToneMapOperator {
+    fn apply(&self, value: f64) -> f64 {
+        match self {
+            ToneMapOperator::Clamp => value,
+            ToneMapOperator::Reinhard => value / (1.0 + value),
+            ToneMapOperator::Aces => {
+                (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14)
+            }
+        }
+    }
+}
+
+/// Everything needed to turn a high dynamic range colour into one that can be
+/// displayed or saved as 8 bits per channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// How much to brighten or darken the image before tone mapping, in
+    /// stops. Each stop doubles the brightness.
+    pub exposure: f64,
+    /// Whether to encode the result with the sRGB transfer function. Lighting
+    /// is calculated in linear values, which look too dark on most screens
+    /// without this. When it is on, the 8-bit colours in the scene are taken
+    /// to be sRGB encoded too, and are decoded before any lighting is done.
+    pub srgb: bool
+}
+
+impl ToneMapping {
+    pub fn new(operator: ToneMapOperator, exposure: f64, srgb: bool) -> Self {
+        ToneMapping {
+            operator,
+            exposure,
+            srgb
+        }
+    }
+
+    /// Clamps without any exposure change or sRGB encoding, which gives the
+    /// same result as [`FloatColor::to_color`].
+    pub fn new_default() -> Self {
+        ToneMapping::new(ToneMapOperator::Clamp, 0.0, false)
+    }
+
+    /// Turns an 8-bit colour from the scene, like a material or light colour,
+    /// into the linear value lighting is calculated with. This undoes the
+    /// sRGB encoding when the output is sRGB encoded, so that a colour lit
+    /// at full strength comes back out of a render unchanged. Alpha is never
+    /// encoded.
+    pub fn linear_color(&self, color: &Color) -> FloatColor {
+        self.linear(FloatColor::from_color(color))
+    }
+
+    /// Like [`ToneMapping::linear_color`], for an 8-bit colour that has
+    /// already been turned into floats, like a pixel of a loaded image.
+    pub fn linear(&self, color: FloatColor) -> FloatColor {
+        if !self.srgb {
+            return color;
+        }
+
+        FloatColor::new(
+            srgb_decode(color.r),
+            srgb_decode(color.g),
+            srgb_decode(color.b),
+            color.a
+        )
+    }
+
+    /// Tone maps the colour. Alpha is only clamped.
+    pub fn map(&self, color: &FloatColor) -> Color {
+        let scale = 2f64.powf(self.exposure);
+        let channel = |value: f64| {
+            let mapped = self
+                .operator
+                .apply((value * scale).max(0.0))
+                .clamp(0.0, 1.0);
+            let encoded = if self.srgb {
+                srgb_encode(mapped)
+            } else {
+                mapped
+            };
+
+            (encoded * 255.0).round() as u8
+        };
+
+        Color::new(
+            channel(color.r),
+            channel(color.g),
+            channel(color.b),
+            (color.a.clamp(0.0, 1.0) * 255.0).round() as u8
+        )
+    }
+}
+
+/// The sRGB transfer function, going from a linear value to an encoded one.
+pub fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The inverse of [`srgb_encode`], going from an encoded value back to a linear
+/// one.
+pub fn srgb_decode(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,92 @@
+use super::*;
+
+#[test]
+fn default_matches_to_color() {
+    let tone_mapping = ToneMapping::new_default();
+
+    for color in [
+        FloatColor::new(0.0, 0.5, 1.0, 1.0),
+        FloatColor::new(2.0, -0.3, 0.123, 0.4)
+    ] {
+        assert_eq!(color.to_color(), tone_mapping.map(&color));
+    }
+}
+
+#[test]
+fn exposure_is_in_stops() {
+    let color = FloatColor::new(0.25, 0.5, 0.1, 1.0);
+
+    let brighter = ToneMapping::new(ToneMapOperator::Clamp, 1.0, false).map(&color);
+    assert_eq!(Color::new(128, 255, 51, 255), brighter);
+
+    let darker = ToneMapping::new(ToneMapOperator::Clamp, -1.0, false).map(&color);
+    assert_eq!(Color::new(32, 64, 13, 255), darker);
+}
+
+#[test]
+fn operators_keep_bright_colours_apart() {
+    let bright = FloatColor::new(2.0, 4.0, 8.0, 1.0);
+
+    let clamped = ToneMapping::new(ToneMapOperator::Clamp, 0.0, false).map(&bright);
+    assert_eq!(Color::new(255, 255, 255, 255), clamped);
+
+    for operator in [ToneMapOperator::Reinhard, ToneMapOperator::Aces] {
+        let mapped = ToneMapping::new(operator, 0.0, false).map(&bright);
+        assert!(mapped.r < mapped.g || mapped.g == 255);
+        assert!(mapped.r < 255);
+    }
+
+    let reinhard = ToneMapping::new(ToneMapOperator::Reinhard, 0.0, false).map(&bright);
+    assert_eq!(Color::new(170, 204, 227, 255), reinhard);
+}
+
+#[test]
+fn aces_maps_black_to_black_and_stays_in_range() {
+    let aces = ToneMapping::new(ToneMapOperator::Aces, 0.0, false);
+
+    assert_eq!(
+        Color::new(0, 0, 0, 255),
+        aces.map(&FloatColor::new(0.0, 0.0, 0.0, 1.0))
+    );
+    assert_eq!(
+        Color::new(255, 255, 255, 255),
+        aces.map(&FloatColor::new(100.0, 100.0, 100.0, 1.0))
+    );
+}
+
+#[test]
+fn srgb_encoding_brightens_mid_tones() {
+    assert_eq!(0.0, srgb_encode(0.0));
+    assert!((srgb_encode(1.0) - 1.0).abs() < 1e-12);
+    assert!((srgb_encode(0.001) - 0.01292).abs() < 1e-12);
+    // Middle grey in linear light is about 18%, which encodes to about half
+    assert!((srgb_encode(0.18) - 0.4614).abs() < 1e-3);
+
+    let color = FloatColor::new(0.18, 0.18, 0.18, 0.5);
+    let encoded = ToneMapping::new(ToneMapOperator::Clamp, 0.0, true).map(&color);
+    assert_eq!(Color::new(118, 118, 118, 128), encoded);
+}
+
+#[test]
+fn srgb_decoding_undoes_encoding() {
+    for value in 0..=255u8 {
+        let encoded = value as f64 / 255.0;
+        let round_trip = srgb_encode(srgb_decode(encoded));
+        assert!((encoded - round_trip).abs() < 1e-9, "{}", value);
+    }
+}
+
+#[test]
+fn scene_colours_are_only_decoded_for_srgb_output() {
+    let color = Color::new(128, 64, 255, 100);
+
+    let linear = ToneMapping::new_default().linear_color(&color);
+    assert_eq!(FloatColor::from_color(&color), linear);
+
+    let srgb = ToneMapping::new(ToneMapOperator::Clamp, 0.0, true);
+    let decoded = srgb.linear_color(&color);
+    assert!((decoded.r - 0.2158605).abs() < 1e-6);
+    assert_eq!(1.0, decoded.b);
+    assert_eq!(100.0 / 255.0, decoded.a);
+    assert_eq!(color, srgb.map(&decoded));
+}
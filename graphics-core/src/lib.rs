@@ -2,4 +2,8 @@ pub mod camera;
 pub mod collision;
 pub mod images;
 pub mod material;
+pub mod render;
 pub mod space;
+
+#[cfg(test)]
+mod test_support;
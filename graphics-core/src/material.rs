@@ -3,18 +3,91 @@ use crate::images::Color;
 // TODO: This will probably need more than just color
 #[derive(Debug, Clone)]
 pub struct Material {
-    color: Color
+    color: Color,
+    /// How strong the shiny highlights from lights are, from 0 to 1.
+    specular: f64,
+    /// The Blinn-Phong exponent. Higher values give smaller, sharper
+    /// highlights.
+    shininess: f64,
+    /// How much the surface acts like a mirror, from 0 to 1. At 1 only the
+    /// reflection is seen and none of the surface's own colour.
+    reflectivity: f64,
+    /// How much light passes through the surface, from 0 to 1.
+    transmission: f64,
+    /// How much light bends when it passes into the material. Air is about 1,
+    /// glass is about 1.5, and diamond is about 2.42.
+    index_of_refraction: f64,
+    /// Whether both sides of the surface can be seen. The front is the side
+    /// the triangle's normal points out of. One sided surfaces disappear when
+    /// seen from behind, but only if the render settings cull back faces.
+    double_sided: bool
 }
 
 impl Material {
     pub fn new(color: Color) -> Material {
-        Material { color }
+        Material {
+            color,
+            specular: 0.0,
+            shininess: 32.0,
+            reflectivity: 0.0,
+            transmission: 0.0,
+            index_of_refraction: 1.5,
+            double_sided: true
+        }
     }
 
     pub fn color(&self) -> &Color {
         &self.color
     }
 
+    pub fn specular(&self) -> f64 {
+        self.specular
+    }
+
+    pub fn set_specular(&mut self, specular: f64) {
+        self.specular = specular;
+    }
+
+    pub fn shininess(&self) -> f64 {
+        self.shininess
+    }
+
+    pub fn set_shininess(&mut self, shininess: f64) {
+        self.shininess = shininess;
+    }
+
+    pub fn reflectivity(&self) -> f64 {
+        self.reflectivity
+    }
+
+    pub fn set_reflectivity(&mut self, reflectivity: f64) {
+        self.reflectivity = reflectivity.clamp(0.0, 1.0);
+    }
+
+    pub fn transmission(&self) -> f64 {
+        self.transmission
+    }
+
+    pub fn set_transmission(&mut self, transmission: f64) {
+        self.transmission = transmission.clamp(0.0, 1.0);
+    }
+
+    pub fn index_of_refraction(&self) -> f64 {
+        self.index_of_refraction
+    }
+
+    pub fn set_index_of_refraction(&mut self, index_of_refraction: f64) {
+        self.index_of_refraction = index_of_refraction;
+    }
+
+    pub fn double_sided(&self) -> bool {
+        self.double_sided
+    }
+
+    pub fn set_double_sided(&mut self, double_sided: bool) {
+        self.double_sided = double_sided;
+    }
+
     pub fn default() -> Material {
         Material::new(Color::from_rgba(0xAAAAAAAAu32))
     }
@@ -0,0 +1,591 @@
+#[cfg(test)]
+mod tests;
+
+pub mod aov;
+pub mod context;
+pub mod path_tracer;
+pub mod progressive;
+pub mod rasterizer;
+pub mod sampling;
+mod shading;
+pub mod stats;
+pub mod wireframe;
+
+use crate::collision::bvh::{Bvh, CullingStats};
+use crate::collision::Ray;
+use crate::images::tone_mapping::ToneMapping;
+use crate::images::{FloatColor, FloatImage};
+use crate::space::frustum::Frustum;
+use crate::space::scene::Scene;
+use context::RenderContext;
+use sampling::{Filter, Sampling};
+use shading::trace_hit;
+use stats::Phase;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Options that control how a scene gets rendered, as opposed to what is in
+/// the scene.
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    /// How many threads to render with. Zero means one thread for each core
+    /// the machine has.
+    pub threads: usize,
+    /// The width and height in pixels of the square tiles the image is split
+    /// into. Each thread renders one tile at a time.
+    pub tile_size: usize,
+    /// How much of a surface's own colour shows when no light is shining on
+    /// it, from 0 to 1.
+    pub ambient: f64,
+    /// How far off of a surface shadow and reflected rays start. Without this,
+    /// rounding errors make surfaces shadow themselves and end up covered in
+    /// speckles, known as shadow acne. Too large and shadows start to come
+    /// unstuck from the objects casting them.
+    pub ray_bias: f64,
+    /// How many times a ray can bounce off of reflective surfaces before the
+    /// renderer stops following it.
+    pub max_depth: usize,
+    /// Where the rays for each pixel are cast. Taking more than one sample
+    /// smooths out jagged edges.
+    pub sampling: Sampling,
+    /// How the samples for each pixel are weighted and averaged together.
+    pub filter: Filter,
+    /// How the high dynamic range result is turned into 8-bit colours.
+    pub tone_mapping: ToneMapping,
+    /// Only render part of the image. `None` renders all of it.
+    pub region: Option<RenderRegion>,
+    /// Whether rays pass straight through the back of surfaces whose
+    /// material isn't double sided. This makes faces pointing the wrong way
+    /// easy to spot, and saves time on closed models whose insides are never
+    /// seen.
+    pub backface_culling: bool
+}
+
+impl RenderSettings {
+    pub fn new_default() -> Self {
+        RenderSettings {
+            threads: 0,
+            tile_size: 32,
+            ambient: 0.1,
+            ray_bias: 1e-4,
+            max_depth: 5,
+            sampling: Sampling::Grid(1),
+            filter: Filter::Box,
+            tone_mapping: ToneMapping::new_default(),
+            region: None,
+            backface_culling: false
+        }
+    }
+
+    /// The actual number of threads to use, after figuring out how many cores
+    /// there are if needed.
+    pub fn thread_count(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads;
+        }
+
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+
+    /// The pixels that actually get rendered for an image of the given size,
+    /// which is the render region cut down to fit inside the image, or the
+    /// whole image if there isn't a region. A region that doesn't overlap the
+    /// image at all gives an area with no width and no height.
+    pub fn render_area(&self, image_size: (usize, usize)) -> Tile {
+        let region = match &self.region {
+            Some(region) => region.area,
+            None => {
+                return Tile {
+                    x: 0,
+                    y: 0,
+                    width: image_size.0,
+                    height: image_size.1
+                }
+            }
+        };
+
+        let x = region.x.min(image_size.0);
+        let y = region.y.min(image_size.1);
+        let width = region.width.min(image_size.0 - x);
+        let height = region.height.min(image_size.1 - y);
+        if width == 0 || height == 0 {
+            return Tile {
+                x,
+                y,
+                width: 0,
+                height: 0
+            };
+        }
+
+        Tile {
+            x,
+            y,
+            width,
+            height
+        }
+    }
+
+    /// The size of the image that comes out of rendering an image of the given
+    /// size, which is just the render area when the region is cropped.
+    pub fn output_size(&self, image_size: (usize, usize)) -> (usize, usize) {
+        match &self.region {
+            Some(region) if region.crop => {
+                let area = self.render_area(image_size);
+                (area.width, area.height)
+            }
+            _ => image_size
+        }
+    }
+}
+
+/// A part of the image to render on its own, such as to re-render a problem
+/// area with more samples without paying for the whole image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderRegion {
+    /// The pixels to render, measured from the top-left corner of the image.
+    pub area: Tile,
+    /// Whether the output is only the region, or the full image with
+    /// everything outside the region left transparent.
+    pub crop: bool
+}
+
+impl RenderRegion {
+    pub fn new(x: usize, y: usize, width: usize, height: usize, crop: bool) -> Self {
+        RenderRegion {
+            area: Tile {
+                x,
+                y,
+                width,
+                height
+            },
+            crop
+        }
+    }
+}
+
+/// A rectangular section of an image, measured in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+/// Splits the image into square tiles going left to right, then top to bottom.
+/// Tiles on the right and bottom edges are smaller when the image size is not a
+/// multiple of the tile size.
+pub fn split_into_tiles(image_size: (usize, usize), tile_size: usize) -> Vec<Tile> {
+    let image = Tile {
+        x: 0,
+        y: 0,
+        width: image_size.0,
+        height: image_size.1
+    };
+
+    split_area_into_tiles(&image, tile_size)
+}
+
+/// Like [`split_into_tiles`], but only covering part of the image. The tiles
+/// start from the top-left corner of the area.
+pub fn split_area_into_tiles(area: &Tile, tile_size: usize) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let (right, bottom) = (area.x + area.width, area.y + area.height);
+    let mut tiles = Vec::new();
+
+    for y in (area.y..bottom).step_by(tile_size) {
+        for x in (area.x..right).step_by(tile_size) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: tile_size.min(right - x),
+                height: tile_size.min(bottom - y)
+            });
+        }
+    }
+
+    tiles
+}
+
+/// Renders an image by casting the rays given by `ray_at` for the samples of
+/// every pixel, and averaging what they see using the reconstruction filter in
+/// the scene's render settings. Along with the position on the image, each ray
+/// is given a random point on the camera's lens. Samples where there is no ray,
+/// because the camera can't see that part of the image, are transparent.
+pub(crate) fn render_image<F>(
+    scene: &Scene,
+    image_size: (usize, usize),
+    context: &RenderContext,
+    frustum: Option<&Frustum>,
+    ray_at: F
+) -> FloatImage
+where
+    F: Fn(f64, f64, (f64, f64)) -> Option<Ray> + Sync
+{
+    let settings = &scene.render_settings;
+    render_float_image(scene, image_size, context, frustum, |geometry, i, j| {
+        let mut rng = pixel_rng(image_size, i, j, 0);
+        let mut total = FloatColor::new(0.0, 0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+
+        for (dx, dy) in settings
+            .sampling
+            .offsets(settings.filter.radius(), &mut rng)
+        {
+            let weight = settings.filter.weight(dx, dy);
+            if weight == 0.0 {
+                continue;
+            }
+
+            let x = (i as f64 + 0.5 + dx) / (image_size.0 as f64);
+            let y = (j as f64 + 0.5 + dy) / (image_size.1 as f64);
+            let lens = (rng.gen(), rng.gen());
+            if let Some(ray) = ray_at(x, y, lens) {
+                total += cast_ray(scene, geometry, &ray) * weight;
+            }
+            total_weight += weight;
+        }
+
+        // Only possible if every sample landed where the filter is zero
+        if total_weight == 0.0 {
+            return FloatColor::new(0.0, 0.0, 0.0, 1.0);
+        }
+        total * (1.0 / total_weight)
+    })
+}
+
+/// Renders one sample for every pixel through the rays given by `ray_at`, for
+/// building an image up over many passes. Each sample is taken at a random
+/// spot the filter covers, and comes back already multiplied by its filter
+/// weight along with the weight itself. Pixels outside the render region
+/// don't get any samples, and when the region is cropped only its pixels are
+/// returned, like [`render_region`].
+///
+/// Negative filter weights count as zero. With only a few samples in a pixel
+/// their weights could add up to almost nothing, and dividing by that would
+/// blow the pixel up, so filters with negative lobes like Mitchell come out a
+/// little softer here than in a full render.
+pub(crate) fn render_image_pass<F>(
+    scene: &Scene,
+    geometry: &RenderGeometry,
+    image_size: (usize, usize),
+    pass: usize,
+    ray_at: F
+) -> Vec<(FloatColor, f64)>
+where
+    F: Fn(f64, f64, (f64, f64)) -> Option<Ray> + Sync
+{
+    let filter = scene.render_settings.filter;
+    let outside = (FloatColor::new(0.0, 0.0, 0.0, 0.0), 0.0);
+    let context = RenderContext::new_default();
+    let (_, samples) = render_prepared_region(
+        scene,
+        image_size,
+        &context,
+        geometry,
+        outside,
+        |geometry, i, j| {
+            let mut rng = pixel_rng(image_size, i, j, pass);
+            let radius = filter.radius();
+            let dx = rng.gen_range(-radius..radius);
+            let dy = rng.gen_range(-radius..radius);
+
+            let weight = filter.weight(dx, dy).max(0.0);
+            if weight == 0.0 {
+                return (FloatColor::new(0.0, 0.0, 0.0, 0.0), 0.0);
+            }
+
+            let x = (i as f64 + 0.5 + dx) / (image_size.0 as f64);
+            let y = (j as f64 + 0.5 + dy) / (image_size.1 as f64);
+            let lens = (rng.gen(), rng.gen());
+            match ray_at(x, y, lens) {
+                Some(ray) => (cast_ray(scene, geometry, &ray) * weight, weight),
+                None => (FloatColor::new(0.0, 0.0, 0.0, 0.0), weight)
+            }
+        }
+    );
+
+    samples
+}
+
+/// The random numbers for a single pixel in a single pass. Every pixel gets
+/// its own so the image doesn't change with the number of threads, and every
+/// pass gets different ones so that repeated passes don't take the same
+/// samples.
+pub(crate) fn pixel_rng(image_size: (usize, usize), i: usize, j: usize, pass: usize) -> StdRng {
+    let pixel = (j * image_size.0 + i) as u64;
+    StdRng::seed_from_u64(((pass as u64) << 32) ^ pixel)
+}
+
+/// Renders an image by asking `pixel_color` for the colour of every pixel in
+/// the render region, given the scene's geometry and the column and row of the
+/// pixel.
+pub(crate) fn render_float_image<P>(
+    scene: &Scene,
+    image_size: (usize, usize),
+    context: &RenderContext,
+    frustum: Option<&Frustum>,
+    pixel_color: P
+) -> FloatImage
+where
+    P: Fn(&RenderGeometry, usize, usize) -> FloatColor + Sync
+{
+    let outside = FloatColor::new(0.0, 0.0, 0.0, 0.0);
+    let ((width, height), pixels) =
+        render_region(scene, image_size, context, frustum, outside, pixel_color);
+    FloatImage::from_pixels(width, height, pixels)
+}
+
+/// Works out values for the pixels in the render region with `pixel_value`.
+/// If the region is cropped only its values are returned, otherwise this is the
+/// same as [`render_canvas`]. The size of the output is returned along with
+/// the values.
+pub(crate) fn render_region<T, P>(
+    scene: &Scene,
+    image_size: (usize, usize),
+    context: &RenderContext,
+    frustum: Option<&Frustum>,
+    outside: T,
+    pixel_value: P
+) -> ((usize, usize), Vec<T>)
+where
+    T: Clone + Send,
+    P: Fn(&RenderGeometry, usize, usize) -> T + Sync
+{
+    let start = Instant::now();
+    let geometry = RenderGeometry::new(scene, frustum);
+    context.set_culling_stats(geometry.culling_stats);
+    context.add_phase_time(Phase::Acceleration, start.elapsed());
+
+    render_prepared_region(scene, image_size, context, &geometry, outside, pixel_value)
+}
+
+/// Like [`render_region`], but with geometry that has already been built.
+pub(crate) fn render_prepared_region<T, P>(
+    scene: &Scene,
+    image_size: (usize, usize),
+    context: &RenderContext,
+    geometry: &RenderGeometry,
+    outside: T,
+    pixel_value: P
+) -> ((usize, usize), Vec<T>)
+where
+    T: Clone + Send,
+    P: Fn(&RenderGeometry, usize, usize) -> T + Sync
+{
+    match &scene.render_settings.region {
+        Some(region) if region.crop => {
+            let area = scene.render_settings.render_area(image_size);
+            let values = render_area(scene, &area, context, geometry, outside, pixel_value);
+            ((area.width, area.height), values)
+        }
+        _ => (
+            image_size,
+            render_canvas(scene, image_size, context, geometry, outside, pixel_value)
+        )
+    }
+}
+
+/// Works out values for the pixels in the render region with `pixel_value`,
+/// and returns them as part of a full image where every pixel outside the
+/// region is `outside`. The values are row by row starting from the top-left
+/// corner.
+pub(crate) fn render_canvas<T, P>(
+    scene: &Scene,
+    image_size: (usize, usize),
+    context: &RenderContext,
+    geometry: &RenderGeometry,
+    outside: T,
+    pixel_value: P
+) -> Vec<T>
+where
+    T: Clone + Send,
+    P: Fn(&RenderGeometry, usize, usize) -> T + Sync
+{
+    let area = scene.render_settings.render_area(image_size);
+    let values = render_area(
+        scene,
+        &area,
+        context,
+        geometry,
+        outside.clone(),
+        pixel_value
+    );
+    if area.width == image_size.0 && area.height == image_size.1 {
+        return values;
+    }
+
+    let mut canvas = vec![outside; image_size.0 * image_size.1];
+    let mut values = values.into_iter();
+    for j in area.y..(area.y + area.height) {
+        for i in area.x..(area.x + area.width) {
+            // Unwrap is safe because there is a value for every pixel
+            canvas[j * image_size.0 + i] = values.next().unwrap();
+        }
+    }
+
+    canvas
+}
+
+/// Works out a value for every pixel in the area with `pixel_value`, which is
+/// given the scene's geometry and the column and row of the pixel in the full
+/// image. The values are returned row by row starting from the top-left corner
+/// of the area.
+///
+/// The area is split into tiles which are shared out between as many threads
+/// as the scene's render settings ask for. Every pixel only depends on its own
+/// position, so the result is the same no matter how many threads are used.
+/// Progress is reported to the context after each tile, and if the context is
+/// cancelled no more tiles are started. Pixels in tiles that never got
+/// rendered are `outside`.
+pub(crate) fn render_area<T, P>(
+    scene: &Scene,
+    area: &Tile,
+    context: &RenderContext,
+    geometry: &RenderGeometry,
+    outside: T,
+    pixel_value: P
+) -> Vec<T>
+where
+    T: Clone + Send,
+    P: Fn(&RenderGeometry, usize, usize) -> T + Sync
+{
+    let start = Instant::now();
+    let settings = &scene.render_settings;
+    let tiles = split_area_into_tiles(area, settings.tile_size);
+    let thread_count = settings.thread_count().min(tiles.len()).max(1);
+    let total_pixels = area.width * area.height;
+
+    let next_tile = AtomicUsize::new(0);
+    let finished_pixels = AtomicUsize::new(0);
+    let finished_tiles: Mutex<Vec<(Tile, Vec<T>)>> = Mutex::new(Vec::new());
+
+    let work = || {
+        // Anything counted on this thread before now wasn't for this render
+        stats::take_counts();
+        while let Some(tile) = tiles.get(next_tile.fetch_add(1, Ordering::Relaxed)) {
+            if context.is_cancelled() {
+                break;
+            }
+
+            let values = render_tile(geometry, tile, &pixel_value);
+            finished_tiles.lock().unwrap().push((*tile, values));
+            context.add_counts(&stats::take_counts());
+
+            let pixels = tile.width * tile.height;
+            let done = finished_pixels.fetch_add(pixels, Ordering::Relaxed) + pixels;
+            context.report_progress(done, total_pixels, start);
+        }
+    };
+
+    if thread_count == 1 {
+        work();
+    } else {
+        thread::scope(|s| {
+            for _ in 0..thread_count {
+                s.spawn(work);
+            }
+        });
+    }
+    context.add_phase_time(Phase::Tracing, start.elapsed());
+
+    let mut pixels = vec![outside; total_pixels];
+    for (tile, values) in finished_tiles.into_inner().unwrap() {
+        let mut values = values.into_iter();
+        for j in tile.y..(tile.y + tile.height) {
+            for i in tile.x..(tile.x + tile.width) {
+                // Unwrap is safe because the tile has a value for every pixel
+                pixels[(j - area.y) * area.width + (i - area.x)] = values.next().unwrap();
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Renders the pixels of a single tile row by row.
+fn render_tile<T, P>(geometry: &RenderGeometry, tile: &Tile, pixel_value: &P) -> Vec<T>
+where
+    P: Fn(&RenderGeometry, usize, usize) -> T
+{
+    let mut values = Vec::with_capacity(tile.width * tile.height);
+
+    for j in tile.y..(tile.y + tile.height) {
+        for i in tile.x..(tile.x + tile.width) {
+            values.push(pixel_value(geometry, i, j));
+        }
+    }
+
+    values
+}
+
+/// How far outside the image, as a fraction of its size, samples can be
+/// taken when they are spread out over the reconstruction filter.
+pub(crate) fn sample_margin(scene: &Scene, image_size: (usize, usize)) -> f64 {
+    let overhang = (scene.render_settings.filter.radius() - 0.5).max(0.0);
+    overhang / image_size.0.min(image_size.1).max(1) as f64
+}
+
+/// The BVHs a render traces rays against. Rays from the camera only need to be
+/// tested against what the camera can see, but anything else, like shadow rays
+/// and reflections, can hit any part of the scene.
+///
+/// Building these is often the slowest part of a quick render, so progressive
+/// renders build them once with [`ProgressiveRenderer::prepare`] and keep
+/// them for every pass.
+///
+/// [`ProgressiveRenderer::prepare`]: progressive::ProgressiveRenderer::prepare
+pub struct RenderGeometry {
+    /// Every triangle in the scene.
+    pub(crate) all: Bvh,
+    /// Just the triangles the camera can see, when some were culled.
+    visible: Option<Bvh>,
+    culling_stats: Option<CullingStats>
+}
+
+impl RenderGeometry {
+    /// Rays from the camera are only tested against what is inside the
+    /// frustum, if there is one.
+    pub(crate) fn new(scene: &Scene, frustum: Option<&Frustum>) -> Self {
+        match frustum {
+            Some(frustum) => {
+                let (all, visible, stats) = Bvh::from_scene_with_view(scene, frustum);
+                RenderGeometry {
+                    all,
+                    visible,
+                    culling_stats: Some(stats)
+                }
+            }
+            None => RenderGeometry {
+                all: Bvh::from_scene(scene),
+                visible: None,
+                culling_stats: None
+            }
+        }
+    }
+
+    /// What rays starting at the camera are tested against.
+    pub(crate) fn camera(&self) -> &Bvh {
+        self.visible.as_ref().unwrap_or(&self.all)
+    }
+}
+
+/// Finds the colour of whatever the camera ray sees in the scene, or the
+/// scene's background if the ray does not hit anything.
+fn cast_ray(scene: &Scene, geometry: &RenderGeometry, ray: &Ray) -> FloatColor {
+    stats::count(|counts| counts.primary_rays += 1);
+    match geometry.camera().closest_hit(ray) {
+        Some(hit) => trace_hit(scene, &geometry.all, &hit, ray, 0),
+        None => scene
+            .background
+            .color_in_direction(ray.direction(), &scene.render_settings.tone_mapping)
+    }
+}
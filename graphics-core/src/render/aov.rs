@@ -0,0 +1,241 @@
+#[cfg(test)]
+mod tests;
+
+use super::context::RenderContext;
+use super::render_region;
+use super::shading::facing_normal;
+use crate::collision::Ray;
+use crate::images::{save_pfm_to_path, Color, RawImage, BLACK};
+use crate::space::frustum::Frustum;
+use crate::space::{bounds::BoundingBox, scene::Scene, Point, Vector};
+
+/// The auxiliary output variables (AOVs) that can be rendered alongside the
+/// final image. These describe the surface seen through each pixel instead of
+/// its colour, which is useful for compositing and for debugging the layout of
+/// a scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// How far along the ray the surface was hit.
+    Depth,
+    /// The normal of the surface, on the side facing the camera.
+    Normal,
+    /// Where in the world the surface was hit.
+    Position,
+    /// The index of the object in `scene.objects`.
+    ObjectId,
+    /// The index of the triangle in its object's mesh.
+    TriangleIndex,
+    /// The barycentric coordinates of the hit on its triangle, see
+    /// [`Collision::uv`](crate::collision::Collision::uv).
+    Uv
+}
+
+/// What the ray through the center of a pixel hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceSample {
+    pub depth: f64,
+    pub normal: Vector,
+    pub position: Point,
+    pub object: usize,
+    pub triangle: usize,
+    pub uv: (f64, f64)
+}
+
+/// Every AOV for every pixel of an image. Pixels where nothing was hit don't
+/// have a sample.
+pub struct AovBuffers {
+    width: usize,
+    height: usize,
+    samples: Vec<Option<SurfaceSample>>
+}
+
+impl AovBuffers {
+    pub fn new(width: usize, height: usize) -> Self {
+        AovBuffers {
+            width,
+            height,
+            samples: vec![None; width * height]
+        }
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_sample(&self, row: usize, col: usize) -> Option<&SurfaceSample> {
+        self.samples[row * self.width + col].as_ref()
+    }
+
+    pub fn set_sample(&mut self, sample: Option<SurfaceSample>, row: usize, col: usize) {
+        self.samples[row * self.width + col] = sample;
+    }
+
+    /// The exact values of one AOV for every pixel, row by row from the
+    /// top-left corner. Single values like depth are repeated in all three
+    /// channels. Pixels that missed have an infinite depth, ids of -1, and
+    /// zero for everything else.
+    pub fn values(&self, aov: Aov) -> Vec<[f32; 3]> {
+        self.samples
+            .iter()
+            .map(|sample| {
+                let sample = match sample {
+                    Some(sample) => sample,
+                    None => {
+                        return match aov {
+                            Aov::Depth => [f32::INFINITY; 3],
+                            Aov::ObjectId | Aov::TriangleIndex => [-1.0; 3],
+                            Aov::Normal | Aov::Position | Aov::Uv => [0.0; 3]
+                        };
+                    }
+                };
+
+                match aov {
+                    Aov::Depth => [sample.depth as f32; 3],
+                    Aov::Normal => vector_values(&sample.normal),
+                    Aov::Position => vector_values(&sample.position),
+                    Aov::ObjectId => [sample.object as f32; 3],
+                    Aov::TriangleIndex => [sample.triangle as f32; 3],
+                    Aov::Uv => [sample.uv.0 as f32, sample.uv.1 as f32, 0.0]
+                }
+            })
+            .collect()
+    }
+
+    /// Turns one AOV into an image that is easy to look at. Depth goes from
+    /// white for the nearest surface to black for the furthest, normals and
+    /// positions are mapped onto red, green, and blue, u and v are red and
+    /// green, and every id gets its own colour. Pixels that missed are black.
+    pub fn to_image(&self, aov: Aov) -> RawImage {
+        let hits: Vec<&SurfaceSample> = self.samples.iter().flatten().collect();
+        let (nearest, furthest) = hits.iter().fold((f64::INFINITY, 0.0f64), |(near, far), s| {
+            (near.min(s.depth), far.max(s.depth))
+        });
+        let mut position_bounds = BoundingBox::new_empty();
+        for sample in &hits {
+            position_bounds.include_point(&sample.position);
+        }
+
+        let mut image = RawImage::new(self.width, self.height);
+        for (index, sample) in self.samples.iter().enumerate() {
+            let color = match sample {
+                None => BLACK,
+                Some(sample) => match aov {
+                    Aov::Depth => {
+                        let range = furthest - nearest;
+                        let closeness = if range > 0.0 {
+                            1.0 - (sample.depth - nearest) / range
+                        } else {
+                            1.0
+                        };
+                        unit_color([closeness; 3])
+                    }
+                    Aov::Normal => {
+                        let n = sample.normal.as_arr();
+                        unit_color([(n[0] + 1.0) / 2.0, (n[1] + 1.0) / 2.0, (n[2] + 1.0) / 2.0])
+                    }
+                    Aov::Position => {
+                        let p = sample.position.as_arr();
+                        let min = position_bounds.min.as_arr();
+                        let size = position_bounds.size().as_arr();
+                        let scaled = |axis: usize| {
+                            if size[axis] > 0.0 {
+                                (p[axis] - min[axis]) / size[axis]
+                            } else {
+                                0.5
+                            }
+                        };
+                        unit_color([scaled(0), scaled(1), scaled(2)])
+                    }
+                    Aov::ObjectId => id_color(sample.object),
+                    Aov::TriangleIndex => id_color(sample.triangle),
+                    Aov::Uv => unit_color([sample.uv.0, sample.uv.1, 0.0])
+                }
+            };
+            image.set_pixel(color, index / self.width, index % self.width);
+        }
+
+        image
+    }
+
+    /// Saves a viewable version of the AOV, as made by
+    /// [`AovBuffers::to_image`], to a PNG file.
+    pub fn save_png(&self, aov: Aov, path: &str) -> Result<(), String> {
+        self.to_image(aov).save_image_to_path(path)
+    }
+
+    /// Saves the exact values of the AOV, as given by [`AovBuffers::values`],
+    /// to a PFM file.
+    pub fn save_pfm(&self, aov: Aov, path: &str) -> Result<(), String> {
+        save_pfm_to_path(path, self.width, self.height, &self.values(aov))
+    }
+}
+
+/// Renders the AOVs by casting the ray given by `ray_at` through the center of
+/// every pixel in the render region. Pixels without a ray are left empty.
+pub(crate) fn render_aovs<F>(
+    scene: &Scene,
+    image_size: (usize, usize),
+    frustum: Option<&Frustum>,
+    ray_at: F
+) -> AovBuffers
+where
+    F: Fn(f64, f64) -> Option<Ray> + Sync
+{
+    let context = RenderContext::new_default();
+    let ((width, height), samples) = render_region(
+        scene,
+        image_size,
+        &context,
+        frustum,
+        None,
+        |geometry, i, j| {
+            let x = (i as f64 + 0.5) / (image_size.0 as f64);
+            let y = (j as f64 + 0.5) / (image_size.1 as f64);
+            let ray = ray_at(x, y)?;
+
+            geometry
+                .camera()
+                .closest_hit(&ray)
+                .map(|hit| SurfaceSample {
+                    depth: hit.collision.distance,
+                    normal: facing_normal(&hit, &ray),
+                    position: hit.collision.point,
+                    object: hit.triangle.object,
+                    triangle: hit.triangle.index,
+                    uv: hit.collision.uv
+                })
+        }
+    );
+
+    AovBuffers {
+        width,
+        height,
+        samples
+    }
+}
+
+fn vector_values(vector: &Vector) -> [f32; 3] {
+    let v = vector.as_arr();
+    [v[0] as f32, v[1] as f32, v[2] as f32]
+}
+
+/// Turns red, green, and blue values from 0 to 1 into an opaque colour.
+fn unit_color(rgb: [f64; 3]) -> Color {
+    let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Color::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2]), 255)
+}
+
+/// A colour picked from the id, so that neighbouring ids look different.
+fn id_color(id: usize) -> Color {
+    let hash = (id as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    Color::new(
+        (hash >> 56) as u8,
+        (hash >> 40) as u8,
+        (hash >> 24) as u8,
+        255
+    )
+}
@@ -0,0 +1,133 @@
+use super::*;
+use crate::camera::{OrthographicCamera, Renderer};
+use crate::space::object::{Mesh, Object};
+use crate::space::Triangle;
+
+/// A rectangle facing the camera covering the given range of x, from the
+/// middle of the view up to the top.
+fn panel(min_x: f64, max_x: f64, z: f64) -> Object {
+    Object::new(Mesh::new(vec![
+        Triangle::new([
+            Point::new(min_x, 0.0, z),
+            Point::new(min_x, 2.0, z),
+            Point::new(max_x, 2.0, z)
+        ]),
+        Triangle::new([
+            Point::new(min_x, 0.0, z),
+            Point::new(max_x, 2.0, z),
+            Point::new(max_x, 0.0, z)
+        ]),
+    ]))
+}
+
+/// A near panel on the top left and a far one on the top right, with nothing
+/// at all in the bottom half.
+fn render_panels() -> AovBuffers {
+    let mut scene = Scene::new_empty();
+    scene.add_object(panel(-2.0, 0.0, 2.0));
+    scene.add_object(panel(0.0, 2.0, 3.0));
+
+    let camera = OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 4.0, 4.0);
+    camera.render_aovs(&scene, (4, 4))
+}
+
+#[test]
+fn samples_describe_what_each_pixel_sees() {
+    let aovs = render_panels();
+    assert_eq!(4, aovs.get_width());
+    assert_eq!(4, aovs.get_height());
+
+    let near = aovs.get_sample(0, 0).unwrap();
+    assert_eq!(2.0, near.depth);
+    assert_eq!(Point::new(0.0, 0.0, -1.0), near.normal);
+    assert_eq!(Point::new(-1.5, 1.5, 2.0), near.position);
+    assert_eq!(0, near.object);
+    assert!(near.triangle < 2);
+
+    let far = aovs.get_sample(1, 3).unwrap();
+    assert_eq!(3.0, far.depth);
+    assert_eq!(1, far.object);
+
+    assert!(aovs.get_sample(3, 0).is_none());
+}
+
+#[test]
+fn values_mark_misses() {
+    let aovs = render_panels();
+
+    let depth = aovs.values(Aov::Depth);
+    assert_eq!(16, depth.len());
+    assert_eq!([2.0; 3], depth[0]);
+    assert_eq!([f32::INFINITY; 3], depth[15]);
+
+    let objects = aovs.values(Aov::ObjectId);
+    assert_eq!([1.0; 3], objects[3]);
+    assert_eq!([-1.0; 3], objects[15]);
+
+    assert_eq!([-1.5, 1.5, 2.0], aovs.values(Aov::Position)[0]);
+    assert_eq!([0.0; 3], aovs.values(Aov::Uv)[15]);
+}
+
+#[test]
+fn uvs_are_where_each_pixel_is_on_its_triangle() {
+    let aovs = render_panels();
+
+    for row in 0..2 {
+        for col in 0..4 {
+            let (u, v) = aovs.get_sample(row, col).unwrap().uv;
+            assert!(u >= 0.0 && v >= 0.0 && u + v <= 1.0);
+        }
+    }
+
+    // The first triangle of the near panel goes up from (-2, 0) to (-2, 2),
+    // then across to (0, 2), so the pixel centered on (-1.5, 1.5) is a quarter
+    // of the way across and half of the way up
+    let near = aovs.get_sample(0, 0).unwrap();
+    assert_eq!(0, near.triangle);
+    assert!((near.uv.0 - 0.5).abs() < 1e-9 && (near.uv.1 - 0.25).abs() < 1e-9);
+    assert_eq!([0.5, 0.25, 0.0], aovs.values(Aov::Uv)[0]);
+
+    let image = aovs.to_image(Aov::Uv);
+    assert_eq!(Color::new(128, 64, 0, 255), image.get_pixel(0, 0).color);
+    assert_eq!(BLACK, image.get_pixel(3, 3).color);
+}
+
+#[test]
+fn images_make_the_buffers_visible() {
+    let aovs = render_panels();
+
+    let depth = aovs.to_image(Aov::Depth);
+    assert_eq!(Color::new(255, 255, 255, 255), depth.get_pixel(0, 0).color);
+    assert_eq!(Color::new(0, 0, 0, 255), depth.get_pixel(0, 3).color);
+
+    let normals = aovs.to_image(Aov::Normal);
+    assert_eq!(Color::new(128, 128, 0, 255), normals.get_pixel(0, 0).color);
+    assert_eq!(BLACK, normals.get_pixel(3, 3).color);
+
+    let objects = aovs.to_image(Aov::ObjectId);
+    assert_eq!(objects.get_pixel(0, 0).color, objects.get_pixel(1, 1).color);
+    assert_ne!(objects.get_pixel(0, 0).color, objects.get_pixel(0, 3).color);
+}
+
+#[test]
+fn buffers_can_be_saved() {
+    let aovs = render_panels();
+    let directory = std::env::temp_dir();
+
+    for (aov, name) in [
+        (Aov::Depth, "depth"),
+        (Aov::TriangleIndex, "triangle"),
+        (Aov::Uv, "uv")
+    ] {
+        let png = directory.join(format!("yapre_aov_{}_{}.png", name, std::process::id()));
+        let pfm = png.with_extension("pfm");
+
+        aovs.save_png(aov, png.to_str().unwrap()).unwrap();
+        aovs.save_pfm(aov, pfm.to_str().unwrap()).unwrap();
+        assert!(png.exists());
+        assert!(pfm.exists());
+
+        std::fs::remove_file(png).unwrap();
+        std::fs::remove_file(pfm).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests;
+
+use super::stats::{Counts, Phase, RenderStats};
+use crate::collision::bvh::CullingStats;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A function that is told how a render is going.
+pub type ProgressCallback<'a> = Box<dyn Fn(&Progress) + Send + Sync + 'a>;
+
+/// How far along a render is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// How much of the image is done, from 0 to 1.
+    pub fraction: f64,
+    /// How long the render has been going.
+    pub elapsed: Duration,
+    /// A guess at how much longer the render will take, based on how fast it
+    /// has gone so far. This is `None` until something has finished.
+    pub remaining: Option<Duration>
+}
+
+/// A flag that can be set from anywhere, like another thread or a UI event, to
+/// ask a render to stop. Clones all share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Lets the caller of a render keep track of it while it runs. Progress is
+/// reported, and the cancellation token checked, every time a tile is
+/// finished.
+pub struct RenderContext<'a> {
+    progress_callback: Option<ProgressCallback<'a>>,
+    cancellation_token: CancellationToken,
+    culling_stats: Mutex<Option<CullingStats>>,
+    stats: Mutex<RenderStats>
+}
+
+impl<'a> RenderContext<'a> {
+    /// A context that doesn't report progress and is never cancelled.
+    pub fn new_default() -> Self {
+        RenderContext {
+            progress_callback: None,
+            cancellation_token: CancellationToken::new(),
+            culling_stats: Mutex::new(None),
+            stats: Mutex::new(RenderStats::default())
+        }
+    }
+
+    /// Sets a function to be called with the progress after every tile. It is
+    /// called from whichever thread finished the tile, so calls can arrive
+    /// slightly out of order.
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(&Progress) + Send + Sync + 'a
+    {
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
+    pub fn set_cancellation_token(&mut self, cancellation_token: CancellationToken) {
+        self.cancellation_token = cancellation_token;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+
+    /// How much of the scene was left out for being outside the camera's view
+    /// in the last render that used this context. This is `None` if nothing
+    /// has been rendered yet, or if the camera can't cull anything.
+    pub fn culling_stats(&self) -> Option<CullingStats> {
+        *self.culling_stats.lock().unwrap()
+    }
+
+    pub(crate) fn set_culling_stats(&self, stats: Option<CullingStats>) {
+        *self.culling_stats.lock().unwrap() = stats;
+    }
+
+    /// How much work went into everything rendered with this context so far.
+    /// Use a new context for each render to get the stats for just that one.
+    pub fn stats(&self) -> RenderStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub(crate) fn add_counts(&self, counts: &Counts) {
+        self.stats.lock().unwrap().add_counts(counts);
+    }
+
+    pub(crate) fn add_phase_time(&self, phase: Phase, time: Duration) {
+        self.stats.lock().unwrap().add_phase_time(phase, time);
+    }
+
+    /// Passes the progress on to the callback, if there is one.
+    pub(crate) fn report_progress(&self, done: usize, total: usize, start: Instant) {
+        let callback = match &self.progress_callback {
+            Some(callback) => callback,
+            None => return
+        };
+
+        let fraction = if total == 0 {
+            1.0
+        } else {
+            done as f64 / total as f64
+        };
+        let elapsed = start.elapsed();
+        let remaining = if fraction > 0.0 {
+            Some(elapsed.mul_f64((1.0 - fraction) / fraction))
+        } else {
+            None
+        };
+
+        callback(&Progress {
+            fraction,
+            elapsed,
+            remaining
+        });
+    }
+}
@@ -0,0 +1,89 @@
+use super::*;
+use crate::camera::{Camera, Renderer};
+use crate::images::Color;
+use crate::material::Material;
+use crate::space::object::{Mesh, Object};
+use crate::space::scene::Scene;
+use crate::space::{Point, Triangle};
+use std::sync::Mutex;
+
+fn scene_with_triangle() -> Scene {
+    let mut triangle = Triangle::new([
+        Point::new(-1.0, -1.0, 4.0),
+        Point::new(0.0, 1.0, 4.0),
+        Point::new(1.0, -1.0, 4.0)
+    ]);
+    triangle.set_material(Material::new(Color::new(255, 0, 0, 255)));
+
+    let mut scene = Scene::new_empty();
+    scene.render_settings.tile_size = 4;
+    scene.add_object(Object::new(Mesh::new(vec![triangle])));
+    scene
+}
+
+#[test]
+fn cancelling_a_token_cancels_its_clones() {
+    let token = CancellationToken::new();
+    let clone = token.clone();
+    assert!(!clone.is_cancelled());
+
+    token.cancel();
+
+    assert!(clone.is_cancelled());
+}
+
+#[test]
+fn progress_estimates_the_time_remaining() {
+    let reports = Mutex::new(Vec::new());
+    let mut context = RenderContext::new_default();
+    context.set_progress_callback(|progress| reports.lock().unwrap().push(*progress));
+
+    let start = Instant::now() - Duration::from_secs(2);
+    context.report_progress(0, 4, start);
+    context.report_progress(1, 4, start);
+    drop(context);
+
+    let reports = reports.into_inner().unwrap();
+    assert_eq!(0.0, reports[0].fraction);
+    assert_eq!(None, reports[0].remaining);
+
+    // A quarter done in two seconds leaves about six more
+    assert_eq!(0.25, reports[1].fraction);
+    let remaining = reports[1].remaining.unwrap().as_secs_f64();
+    assert!((remaining - 6.0).abs() < 0.5, "remaining {}", remaining);
+}
+
+#[test]
+fn renders_report_progress_until_done() {
+    let scene = scene_with_triangle();
+    let fractions = Mutex::new(Vec::new());
+    let mut context = RenderContext::new_default();
+    context.set_progress_callback(|progress| fractions.lock().unwrap().push(progress.fraction));
+
+    Camera::new_default().render_with_context(&scene, (10, 10), &context);
+    drop(context);
+
+    // One report for each of the nine tiles
+    let mut fractions = fractions.into_inner().unwrap();
+    assert_eq!(9, fractions.len());
+    fractions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(1.0, *fractions.last().unwrap());
+}
+
+#[test]
+fn cancelled_renders_leave_the_image_unfinished() {
+    let scene = scene_with_triangle();
+    let mut context = RenderContext::new_default();
+    let token = CancellationToken::new();
+    context.set_cancellation_token(token.clone());
+    token.cancel();
+
+    let image = Camera::new_default().render_hdr_with_context(&scene, (10, 10), &context);
+
+    assert_eq!(10, image.get_width());
+    for j in 0..10 {
+        for i in 0..10 {
+            assert_eq!(0.0, image.get_pixel(j, i).a);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+#[cfg(test)]
+mod tests;
+
+use super::aov::{render_aovs, AovBuffers};
+use super::context::RenderContext;
+use super::progressive::ProgressiveRenderer;
+use super::shading::{facing_normal, refract, schlick};
+use super::stats;
+use super::{pixel_rng, render_float_image, render_prepared_region, RenderGeometry};
+use crate::camera::{Camera, Renderer};
+use crate::collision::{
+    bvh::{Bvh, Hit},
+    Ray
+};
+use crate::images::{FloatColor, FloatImage};
+use crate::space::{scene::Scene, Point, Vector};
+
+use rand::Rng;
+
+use std::f64::consts::PI;
+
+/// Paths always get to bounce this many times before Russian roulette is
+/// allowed to end them early.
+const MIN_BOUNCES: usize = 3;
+/// The highest chance a path has of surviving Russian roulette. Keeping this
+/// below one means even very bright paths can't bounce forever.
+const MAX_SURVIVAL: f64 = 0.95;
+
+/// Renders a scene with unbiased Monte Carlo path tracing, which follows
+/// random paths of light bouncing around the scene so that surfaces are also
+/// lit by light coming off of other surfaces.
+///
+/// Diffuse surfaces bounce light in cosine weighted random directions and
+/// sample every light directly at each bounce. Mirrors and transparent
+/// materials pick one way to send the path based on how much of the light goes
+/// each way. Paths end after the maximum number of bounces, or earlier through
+/// Russian roulette once they are carrying very little light. The ambient
+/// render setting is not used since the bounced light takes its place.
+///
+/// Every pixel uses its own seeded random numbers, so rendering the same scene
+/// twice gives exactly the same image.
+#[derive(Debug, Clone)]
+pub struct PathTracer {
+    camera: Camera,
+    samples_per_pixel: usize,
+    max_bounces: usize
+}
+
+impl PathTracer {
+    pub fn new(camera: Camera, samples_per_pixel: usize, max_bounces: usize) -> Self {
+        PathTracer {
+            camera,
+            samples_per_pixel: samples_per_pixel.max(1),
+            max_bounces
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+
+    /// How many paths are traced and averaged for each pixel. More samples
+    /// means less noise but a longer render.
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    pub fn set_samples_per_pixel(&mut self, samples_per_pixel: usize) {
+        self.samples_per_pixel = samples_per_pixel.max(1);
+    }
+
+    /// How many times a path can bounce after first hitting the scene. Zero
+    /// gives direct lighting only.
+    pub fn max_bounces(&self) -> usize {
+        self.max_bounces
+    }
+
+    pub fn set_max_bounces(&mut self, max_bounces: usize) {
+        self.max_bounces = max_bounces;
+    }
+
+    /// Follows a single random path backwards from the camera and adds up the
+    /// light that reaches the camera along it.
+    fn radiance<R: Rng>(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        ray: &Ray,
+        rng: &mut R
+    ) -> FloatColor {
+        let bias = scene.render_settings.ray_bias;
+        let mut result = FloatColor::new(0.0, 0.0, 0.0, 1.0);
+        // How much of the light found at the current bounce makes it back to
+        // the camera.
+        let mut throughput = FloatColor::new(1.0, 1.0, 1.0, 1.0);
+        let mut ray = Ray::new(*ray.location(), *ray.direction());
+
+        for bounce in 0..=self.max_bounces {
+            // Only the first ray comes from the camera, so after that anything
+            // in the scene can be hit
+            let bvh = if bounce == 0 {
+                stats::count(|counts| counts.primary_rays += 1);
+                geometry.camera()
+            } else {
+                stats::count(|counts| counts.secondary_rays += 1);
+                &geometry.all
+            };
+            let hit = match bvh.closest_hit(&ray) {
+                Some(hit) => hit,
+                None => {
+                    // The background lights the scene too
+                    result += throughput
+                        * scene.background.color_in_direction(
+                            ray.direction(),
+                            &scene.render_settings.tone_mapping
+                        );
+                    break;
+                }
+            };
+
+            let material = hit.triangle.material();
+            let base = scene
+                .render_settings
+                .tone_mapping
+                .linear_color(material.color());
+            let direction = *ray.direction();
+            let normal = facing_normal(&hit, &ray);
+            let point = hit.collision.point;
+            let reflected_direction = direction - normal * (2.0 * direction.dot(&normal));
+
+            // Choose one way for the path to carry on, with the same weights
+            // the ray caster uses to blend them. Since the chance of picking
+            // each one matches its weight, the throughput doesn't need to be
+            // scaled to make up for it.
+            let transmission = material.transmission();
+            let reflectivity = material.reflectivity();
+            let choice: f64 = rng.gen();
+            ray = if choice < transmission {
+                let entering = hit.triangle.triangle.normal().dot(&direction) < 0.0;
+                let ior = material.index_of_refraction();
+                let (n1, n2) = if entering { (1.0, ior) } else { (ior, 1.0) };
+
+                match refract(&direction, &normal, n1 / n2) {
+                    Some((refracted_direction, cos_t))
+                        if rng.gen::<f64>() >= schlick(-direction.dot(&normal), cos_t, n1, n2) =>
+                    {
+                        throughput = throughput * base;
+                        Ray::new(point - normal * bias, refracted_direction)
+                    }
+                    // Either total internal reflection or Fresnel reflection
+                    _ => Ray::new(point + normal * bias, reflected_direction)
+                }
+            } else if choice < transmission + (1.0 - transmission) * reflectivity {
+                Ray::new(point + normal * bias, reflected_direction)
+            } else {
+                throughput = throughput * base;
+                result += throughput * direct_light(scene, &geometry.all, &hit, &normal);
+                Ray::new(
+                    point + normal * bias,
+                    cosine_weighted_direction(&normal, rng)
+                )
+            };
+
+            if bounce >= MIN_BOUNCES {
+                let survival = throughput
+                    .r
+                    .max(throughput.g)
+                    .max(throughput.b)
+                    .min(MAX_SURVIVAL);
+                if rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        result.a = 1.0;
+        result
+    }
+}
+
+impl Renderer for PathTracer {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        // Samples stay inside their pixels, so nothing outside the image is
+        // needed
+        let frustum = self.camera.frustum(0.0);
+        render_float_image(
+            scene,
+            image_size,
+            context,
+            frustum.as_ref(),
+            |geometry, i, j| {
+                let mut rng = pixel_rng(image_size, i, j, 0);
+                let mut total = FloatColor::new(0.0, 0.0, 0.0, 0.0);
+
+                for _ in 0..self.samples_per_pixel {
+                    // Spread the samples out over the whole pixel
+                    let x = (i as f64 + rng.gen::<f64>()) / (image_size.0 as f64);
+                    let y = (j as f64 + rng.gen::<f64>()) / (image_size.1 as f64);
+
+                    if let Some(ray) = self.camera.image_ray(x, y, (rng.gen(), rng.gen())) {
+                        total += self.radiance(scene, geometry, &ray, &mut rng);
+                    }
+                }
+
+                total * (1.0 / self.samples_per_pixel as f64)
+            }
+        )
+    }
+
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        render_aovs(
+            scene,
+            image_size,
+            self.camera.frustum(0.0).as_ref(),
+            |x, y| self.camera.image_ray(x, y, (0.5, 0.5))
+        )
+    }
+}
+
+impl ProgressiveRenderer for PathTracer {
+    fn prepare(&self, scene: &Scene, _image_size: (usize, usize)) -> RenderGeometry {
+        RenderGeometry::new(scene, self.camera.frustum(0.0).as_ref())
+    }
+
+    /// Traces a single path for every pixel, no matter how many samples per
+    /// pixel the tracer is set to take.
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        let outside = (FloatColor::new(0.0, 0.0, 0.0, 0.0), 0.0);
+        let context = RenderContext::new_default();
+        let (_, samples) = render_prepared_region(
+            scene,
+            image_size,
+            &context,
+            geometry,
+            outside,
+            |geometry, i, j| {
+                let mut rng = pixel_rng(image_size, i, j, pass);
+                let x = (i as f64 + rng.gen::<f64>()) / (image_size.0 as f64);
+                let y = (j as f64 + rng.gen::<f64>()) / (image_size.1 as f64);
+
+                match self.camera.image_ray(x, y, (rng.gen(), rng.gen())) {
+                    Some(ray) => (self.radiance(scene, geometry, &ray, &mut rng), 1.0),
+                    None => (FloatColor::new(0.0, 0.0, 0.0, 0.0), 1.0)
+                }
+            }
+        );
+
+        samples
+    }
+}
+
+/// The light arriving straight from every light in the scene that isn't
+/// blocked, already multiplied by how much the surface faces each light.
+fn direct_light(scene: &Scene, bvh: &Bvh, hit: &Hit, normal: &Vector) -> FloatColor {
+    let bias = scene.render_settings.ray_bias;
+    let point = hit.collision.point;
+    let mut result = FloatColor::new(0.0, 0.0, 0.0, 0.0);
+
+    for light in &scene.lights {
+        let illumination = match light.illumination_at(&point) {
+            Some(illumination) => illumination,
+            None => continue
+        };
+
+        let cos = normal.dot(&illumination.direction);
+        if cos <= 0.0 {
+            continue;
+        }
+
+        let shadow_ray = Ray::new(point + *normal * bias, illumination.direction);
+        stats::count(|counts| counts.shadow_rays += 1);
+        if bvh.any_hit(&shadow_ray, illumination.distance - bias) {
+            continue;
+        }
+
+        result += scene
+            .render_settings
+            .tone_mapping
+            .linear_color(&illumination.color)
+            * (illumination.strength * cos);
+    }
+
+    result
+}
+
+/// Picks a random direction on the side of the surface the normal points
+/// towards. Directions are more likely the closer they are to the normal, in
+/// proportion to the cosine of the angle between them, which exactly cancels
+/// out the cosine in the lighting of diffuse surfaces.
+fn cosine_weighted_direction<R: Rng>(normal: &Vector, rng: &mut R) -> Vector {
+    // Any vector that isn't parallel to the normal will do to build the rest
+    // of the basis from
+    let helper = if normal.x.abs() > 0.9 {
+        Point::new(0.0, 1.0, 0.0)
+    } else {
+        Point::new(1.0, 0.0, 0.0)
+    };
+    let tangent = normal.cross(&helper).normalized();
+    let bitangent = normal.cross(&tangent);
+
+    // Pick a point on a disk and project it up onto the hemisphere
+    let u: f64 = rng.gen();
+    let angle = 2.0 * PI * rng.gen::<f64>();
+    let radius = u.sqrt();
+
+    tangent * (radius * angle.cos())
+        + bitangent * (radius * angle.sin())
+        + *normal * (1.0 - u).sqrt()
+}
@@ -0,0 +1,180 @@
+use super::*;
+use crate::camera::PerspectiveCamera;
+use crate::images::{Color, RawImage, WHITE};
+use crate::material::Material;
+use crate::space::background::Background;
+use crate::space::lighting::{DirectionalLight, PointLight};
+use crate::space::object::Object;
+use crate::test_support::quad;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// A square at the given depth facing the camera at the origin.
+fn wall(z: f64, material: Material) -> Object {
+    quad(
+        [
+            Point::new(-5.0, -1.0, z),
+            Point::new(-5.0, 5.0, z),
+            Point::new(5.0, 5.0, z),
+            Point::new(5.0, -1.0, z)
+        ],
+        material
+    )
+}
+
+/// A floor below the camera that reaches back to the wall.
+fn floor(material: Material) -> Object {
+    quad(
+        [
+            Point::new(-5.0, -1.0, -5.0),
+            Point::new(-5.0, -1.0, 5.0),
+            Point::new(5.0, -1.0, 5.0),
+            Point::new(5.0, -1.0, -5.0)
+        ],
+        material
+    )
+}
+
+fn sun(direction: Point) -> Box<DirectionalLight> {
+    Box::new(DirectionalLight::new(direction, WHITE, 1.0))
+}
+
+fn center_pixel(image: &RawImage) -> Color {
+    image
+        .get_pixel(image.get_height() / 2, image.get_width() / 2)
+        .color
+        .clone()
+}
+
+#[test]
+fn cosine_weighted_directions_favor_the_normal() {
+    let normal = Point::new(0.0, 0.0, -1.0);
+    let mut rng = StdRng::seed_from_u64(7);
+
+    let samples = 20_000;
+    let mut total_cos = 0.0;
+    for _ in 0..samples {
+        let direction = cosine_weighted_direction(&normal, &mut rng);
+        assert!((direction.length() - 1.0).abs() < 1e-9);
+
+        let cos = direction.dot(&normal);
+        assert!(cos >= 0.0);
+        total_cos += cos;
+    }
+
+    // The average cosine for this distribution is two thirds, where evenly
+    // spread directions would only average a half.
+    let average = total_cos / samples as f64;
+    assert!(
+        (average - 2.0 / 3.0).abs() < 0.01,
+        "average cosine {}",
+        average
+    );
+}
+
+#[test]
+fn direct_lighting_matches_the_ray_caster() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.add_object(wall(3.0, Material::new(Color::new(200, 100, 50, 255))));
+    scene.lights.push(sun(Point::new(0.0, -1.0, 1.0)));
+
+    let camera = Camera::Perspective(PerspectiveCamera::new_default());
+    let cast = camera.render(&scene, (9, 9));
+    let traced = PathTracer::new(camera, 4, 0).render(&scene, (9, 9));
+
+    assert_eq!(center_pixel(&cast), center_pixel(&traced));
+}
+
+#[test]
+fn surfaces_are_lit_by_light_bouncing_off_other_surfaces() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.threads = 1;
+    scene.add_object(wall(5.0, Material::new(WHITE)));
+    scene.add_object(floor(Material::new(WHITE)));
+    // Shining straight down, so the wall doesn't get any light directly
+    scene.lights.push(sun(Point::new(0.0, -1.0, 0.0)));
+
+    let camera = Camera::Perspective(PerspectiveCamera::new_default());
+    let direct = PathTracer::new(camera.clone(), 16, 0).render(&scene, (5, 5));
+    let bounced = PathTracer::new(camera, 16, 3).render(&scene, (5, 5));
+
+    assert_eq!(0, center_pixel(&direct).r);
+    assert!(center_pixel(&bounced).r > 20);
+}
+
+#[test]
+fn mirrors_show_what_is_in_front_of_them() {
+    let mut scene = Scene::new_empty();
+    let mut mirror = Material::new(WHITE);
+    mirror.set_reflectivity(1.0);
+    scene.add_object(wall(5.0, mirror));
+    scene.add_object(wall(-5.0, Material::new(Color::new(0, 255, 0, 255))));
+    // Between the two walls so the mirror doesn't cast a shadow over the other
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(0.0, 0.0, -2.0),
+        WHITE,
+        9.0
+    )));
+
+    let camera = Camera::Perspective(PerspectiveCamera::new_default());
+    let image = PathTracer::new(camera, 1, 1).render(&scene, (5, 5));
+
+    let color = center_pixel(&image);
+    assert_eq!(0, color.r);
+    assert!(color.g > 240);
+}
+
+#[test]
+fn renders_are_repeatable_with_any_number_of_threads() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.tile_size = 3;
+    scene.add_object(wall(5.0, Material::new(WHITE)));
+    scene.add_object(floor(Material::new(Color::new(255, 0, 0, 255))));
+    scene.lights.push(sun(Point::new(0.3, -1.0, 0.5)));
+
+    let tracer = PathTracer::new(Camera::new_default(), 4, 4);
+    scene.render_settings.threads = 1;
+    let single = tracer.render(&scene, (10, 10));
+    scene.render_settings.threads = 4;
+    let threaded = tracer.render(&scene, (10, 10));
+
+    assert_eq!(single.get_image_data(), threaded.get_image_data());
+}
+
+#[test]
+fn the_background_lights_the_scene() {
+    let mut scene = Scene::new_empty();
+    scene.background = Background::Solid(Color::new(255, 255, 255, 255));
+    scene.add_object(floor(Material::new(Color::new(255, 0, 0, 255))));
+
+    // Looking down at the floor, which is only lit by the white sky
+    let camera = Camera::Perspective(PerspectiveCamera::new(
+        Point::new(0.0, 1.0, 0.0),
+        Point::new(0.0, -1.0, 0.5),
+        Point::new(0.0, 1.0, 0.0),
+        0.5,
+        1.0
+    ));
+    let image = PathTracer::new(camera, 4, 1).render(&scene, (3, 3));
+
+    // A white floor under a white sky with nothing else around would be
+    // exactly white, so a red one is exactly red
+    let color = center_pixel(&image);
+    assert_eq!(255, color.r);
+    assert_eq!(0, color.g);
+}
+
+#[test]
+fn every_sample_is_counted_as_a_primary_ray() {
+    let mut scene = Scene::new_empty();
+    scene.add_object(wall(3.0, Material::new(WHITE)));
+
+    let camera = Camera::Perspective(PerspectiveCamera::new_default());
+    let context = RenderContext::new_default();
+    PathTracer::new(camera, 3, 0).render_hdr_with_context(&scene, (4, 4), &context);
+
+    let stats = context.stats();
+    assert_eq!(48, stats.primary_rays);
+    assert_eq!(0, stats.secondary_rays);
+}
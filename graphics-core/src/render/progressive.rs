@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests;
+
+use super::RenderGeometry;
+use crate::images::tone_mapping::ToneMapping;
+use crate::images::{FloatColor, FloatImage, RawImage};
+use crate::space::scene::Scene;
+
+/// A renderer that can build an image up a little at a time, taking one more
+/// sample for every pixel on each pass.
+pub trait ProgressiveRenderer {
+    /// Builds the geometry that passes trace rays against. It can be kept and
+    /// used for every pass until the scene or the renderer changes.
+    fn prepare(&self, scene: &Scene, image_size: (usize, usize)) -> RenderGeometry;
+
+    /// Renders a single sample for every pixel, row by row from the top-left
+    /// corner, using geometry from [`ProgressiveRenderer::prepare`]. Each
+    /// sample is its colour already multiplied by its weight, along with the
+    /// weight itself. Different passes must take different samples so that
+    /// adding them up converges on the finished image.
+    ///
+    /// The samples follow the scene's render region the same way a full
+    /// render does: pixels outside it get a weight of zero, and a cropped
+    /// region only has samples for its own pixels. See
+    /// [`RenderSettings::output_size`](super::RenderSettings::output_size).
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)>;
+
+    /// Like [`ProgressiveRenderer::render_prepared_pass`], but preparing the
+    /// geometry just for this one pass.
+    fn render_pass(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        let geometry = self.prepare(scene, image_size);
+        self.render_prepared_pass(scene, &geometry, image_size, pass)
+    }
+}
+
+/// Adds up the samples from many passes of a [`ProgressiveRenderer`] so the
+/// average so far can be looked at whenever it is wanted, such as after every
+/// pass to watch the image get less noisy.
+pub struct AccumulationBuffer {
+    /// The size of the image the passes render, which is bigger than the
+    /// buffer when the render region is cropped.
+    image_size: (usize, usize),
+    width: usize,
+    height: usize,
+    totals: Vec<FloatColor>,
+    weights: Vec<f64>,
+    passes: usize,
+    /// What the passes trace rays against, kept until the buffer is reset.
+    geometry: Option<RenderGeometry>
+}
+
+impl AccumulationBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        AccumulationBuffer {
+            image_size: (width, height),
+            width,
+            height,
+            totals: vec![FloatColor::new(0.0, 0.0, 0.0, 0.0); width * height],
+            weights: vec![0.0; width * height],
+            passes: 0,
+            geometry: None
+        }
+    }
+
+    /// How wide the image the buffer builds up is, which is only the width of
+    /// the render region if it is cropped.
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// How many passes have been added since the buffer was created or last
+    /// reset.
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    /// Throws away everything added so far, along with the geometry the
+    /// passes were traced against. This needs to happen whenever the scene or
+    /// renderer changes, or the old samples will smear into the new ones.
+    pub fn reset(&mut self) {
+        self.totals.fill(FloatColor::new(0.0, 0.0, 0.0, 0.0));
+        self.weights.fill(0.0);
+        self.passes = 0;
+        self.geometry = None;
+    }
+
+    /// Renders one more pass and adds it to the buffer. The renderer prepares
+    /// the scene's geometry on the first pass after the buffer is created or
+    /// reset, and the same geometry is used for every pass after that.
+    ///
+    /// The buffer takes on the size of the scene's output, so it shrinks to
+    /// the render region when that is cropped. If that changes the size,
+    /// everything added so far is thrown away.
+    pub fn add_pass<R>(&mut self, renderer: &R, scene: &Scene)
+    where
+        R: ProgressiveRenderer + ?Sized
+    {
+        let image_size = self.image_size;
+        let (width, height) = scene.render_settings.output_size(image_size);
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.totals = vec![FloatColor::new(0.0, 0.0, 0.0, 0.0); width * height];
+            self.weights = vec![0.0; width * height];
+            self.reset();
+        }
+
+        let geometry = self
+            .geometry
+            .get_or_insert_with(|| renderer.prepare(scene, image_size));
+        let samples = renderer.render_prepared_pass(scene, geometry, image_size, self.passes);
+        self.add_samples(&samples);
+    }
+
+    /// Adds a pass worth of samples, one for each pixel row by row, to the
+    /// buffer.
+    pub fn add_samples(&mut self, samples: &[(FloatColor, f64)]) {
+        assert_eq!(self.totals.len(), samples.len());
+
+        for (index, (color, weight)) in samples.iter().enumerate() {
+            self.totals[index] += *color;
+            self.weights[index] += weight;
+        }
+        self.passes += 1;
+    }
+
+    /// The average of every sample added so far. Pixels that haven't had any
+    /// samples yet are transparent, like pixels outside the render region in a
+    /// full render.
+    pub fn hdr_image(&self) -> FloatImage {
+        let pixels = self
+            .totals
+            .iter()
+            .zip(&self.weights)
+            .map(|(total, weight)| {
+                if *weight == 0.0 {
+                    FloatColor::new(0.0, 0.0, 0.0, 0.0)
+                } else {
+                    *total * (1.0 / weight)
+                }
+            })
+            .collect();
+
+        FloatImage::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// The average of every sample added so far, tone mapped down to 8 bits.
+    pub fn image(&self, tone_mapping: &ToneMapping) -> RawImage {
+        self.hdr_image().to_raw_image(tone_mapping)
+    }
+}
@@ -0,0 +1,246 @@
+use super::*;
+use crate::camera::{Camera, OrthographicCamera, Renderer};
+use crate::images::{Color, RED, WHITE};
+use crate::material::Material;
+use crate::render::path_tracer::PathTracer;
+use crate::render::sampling::Sampling;
+use crate::render::RenderRegion;
+use crate::space::lighting::DirectionalLight;
+use crate::space::object::{Mesh, Object};
+use crate::space::{Point, Triangle};
+use crate::test_support::unlit_scene;
+
+/// A red triangle covering about half of what the camera sees, lit so that
+/// it shows its colour exactly.
+fn half_covered_scene() -> Scene {
+    let mut scene = unlit_scene();
+    let mut triangle = Triangle::new([
+        Point::new(-2.0, 2.0, 1.0),
+        Point::new(1.7, -2.0, 1.0),
+        Point::new(-2.0, -2.0, 1.0)
+    ]);
+    triangle.set_material(Material::new(RED));
+    scene.add_object(Object::new(Mesh::new(vec![triangle])));
+
+    scene
+}
+
+#[test]
+fn samples_are_averaged_by_weight() {
+    let mut buffer = AccumulationBuffer::new(2, 1);
+    assert_eq!(
+        Color::new(0, 0, 0, 0),
+        buffer
+            .image(&ToneMapping::new_default())
+            .get_pixel(0, 0)
+            .color
+    );
+
+    buffer.add_samples(&[
+        (FloatColor::new(1.0, 0.0, 0.0, 1.0), 1.0),
+        (FloatColor::new(0.0, 0.0, 0.0, 0.0), 0.0)
+    ]);
+    buffer.add_samples(&[
+        (FloatColor::new(0.0, 0.0, 3.0, 3.0), 3.0),
+        (FloatColor::new(0.0, 0.5, 0.0, 0.5), 0.5)
+    ]);
+
+    assert_eq!(2, buffer.passes());
+    let image = buffer.image(&ToneMapping::new_default());
+    assert_eq!(Color::new(64, 0, 191, 255), image.get_pixel(0, 0).color);
+    assert_eq!(Color::new(0, 255, 0, 255), image.get_pixel(0, 1).color);
+
+    buffer.reset();
+    assert_eq!(0, buffer.passes());
+    assert_eq!(
+        Color::new(0, 0, 0, 0),
+        buffer
+            .image(&ToneMapping::new_default())
+            .get_pixel(0, 0)
+            .color
+    );
+}
+
+#[test]
+fn camera_passes_converge_on_a_supersampled_render() {
+    let mut scene = half_covered_scene();
+    let camera = Camera::Ortho(OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0));
+
+    let mut buffer = AccumulationBuffer::new(8, 8);
+    for _ in 0..256 {
+        buffer.add_pass(&camera, &scene);
+    }
+    let progressive = buffer.image(&ToneMapping::new_default());
+
+    scene.render_settings.sampling = Sampling::Grid(16);
+    let supersampled = camera.render(&scene, (8, 8));
+
+    for row in 0..8 {
+        for col in 0..8 {
+            let expected = supersampled.get_pixel(row, col).color.r as i32;
+            let actual = progressive.get_pixel(row, col).color.r as i32;
+            assert!(
+                (expected - actual).abs() <= 24,
+                "pixel ({}, {}) was {} but should be close to {}",
+                row,
+                col,
+                actual,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn passes_follow_the_render_region() {
+    let mut scene = half_covered_scene();
+    let camera = Camera::Ortho(OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0));
+    let mut buffer = AccumulationBuffer::new(8, 8);
+
+    for crop in [false, true] {
+        scene.render_settings.region = Some(RenderRegion::new(2, 1, 4, 3, crop));
+        buffer.add_pass(&camera, &scene);
+        let progressive = buffer.image(&ToneMapping::new_default());
+        let full = camera.render(&scene, (8, 8));
+
+        assert_eq!(
+            (full.get_width(), full.get_height()),
+            (progressive.get_width(), progressive.get_height())
+        );
+        assert_eq!(
+            (full.get_width(), full.get_height()),
+            (buffer.get_width(), buffer.get_height())
+        );
+        // Only the pixels in the region are opaque, just like a full render
+        for row in 0..full.get_height() {
+            for col in 0..full.get_width() {
+                assert_eq!(
+                    full.get_pixel(row, col).color.a,
+                    progressive.get_pixel(row, col).color.a
+                );
+            }
+        }
+    }
+
+    // Cropping changed the size of the buffer, which started it over
+    assert_eq!(1, buffer.passes());
+}
+
+#[test]
+fn each_pass_takes_new_samples() {
+    let scene = half_covered_scene();
+    let camera = OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0);
+
+    let first = camera.render_pass(&scene, (8, 8), 0);
+    let second = camera.render_pass(&scene, (8, 8), 1);
+    assert_eq!(64, first.len());
+    assert_ne!(first, second);
+}
+
+#[test]
+fn path_traced_passes_match_a_full_render_when_the_lighting_is_flat() {
+    let mut scene = Scene::new_empty();
+    let mut wall = Triangle::new([
+        Point::new(-10.0, -10.0, 3.0),
+        Point::new(0.0, 10.0, 3.0),
+        Point::new(10.0, -10.0, 3.0)
+    ]);
+    wall.set_material(Material::new(Color::new(200, 100, 50, 255)));
+    scene.add_object(Object::new(Mesh::new(vec![wall])));
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, 0.0, 1.0),
+        WHITE,
+        1.0
+    )));
+
+    let tracer = PathTracer::new(Camera::new_default(), 1, 0);
+    let mut buffer = AccumulationBuffer::new(6, 6);
+    buffer.add_pass(&tracer, &scene);
+    buffer.add_pass(&tracer, &scene);
+
+    assert_eq!(
+        tracer.render(&scene, (6, 6)).get_image_data(),
+        buffer.image(&ToneMapping::new_default()).get_image_data()
+    );
+}
+
+/// Passes everything on to a camera, keeping track of how many times the
+/// geometry gets prepared.
+struct CountingRenderer {
+    camera: OrthographicCamera,
+    prepared: std::cell::Cell<usize>
+}
+
+impl ProgressiveRenderer for CountingRenderer {
+    fn prepare(&self, scene: &Scene, image_size: (usize, usize)) -> RenderGeometry {
+        self.prepared.set(self.prepared.get() + 1);
+        self.camera.prepare(scene, image_size)
+    }
+
+    fn render_prepared_pass(
+        &self,
+        scene: &Scene,
+        geometry: &RenderGeometry,
+        image_size: (usize, usize),
+        pass: usize
+    ) -> Vec<(FloatColor, f64)> {
+        self.camera
+            .render_prepared_pass(scene, geometry, image_size, pass)
+    }
+}
+
+#[test]
+fn geometry_is_only_prepared_again_after_a_reset() {
+    let scene = half_covered_scene();
+    let renderer = CountingRenderer {
+        camera: OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0),
+        prepared: std::cell::Cell::new(0)
+    };
+
+    let mut buffer = AccumulationBuffer::new(8, 8);
+    for _ in 0..4 {
+        buffer.add_pass(&renderer, &scene);
+    }
+    assert_eq!(1, renderer.prepared.get());
+
+    // Reusing the geometry doesn't change what the passes see
+    let mut fresh = AccumulationBuffer::new(8, 8);
+    for pass in 0..4 {
+        fresh.add_samples(&renderer.camera.render_pass(&scene, (8, 8), pass));
+    }
+    assert_eq!(fresh.hdr_image(), buffer.hdr_image());
+
+    buffer.reset();
+    buffer.add_pass(&renderer, &scene);
+    assert_eq!(2, renderer.prepared.get());
+}
+
+#[test]
+fn mitchell_passes_stay_within_the_colours_in_the_scene() {
+    use crate::render::sampling::Filter;
+
+    let mut scene = half_covered_scene();
+    scene.render_settings.filter = Filter::Mitchell;
+    let camera = OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0);
+
+    // Every colour in the scene is between black and red, so any average of
+    // them must be too, no matter how few samples have been taken
+    let mut buffer = AccumulationBuffer::new(16, 16);
+    for _ in 0..8 {
+        buffer.add_pass(&camera, &scene);
+        let image = buffer.hdr_image();
+        for row in 0..16 {
+            for col in 0..16 {
+                let pixel = image.get_pixel(row, col);
+                assert!(
+                    (0.0..=1.0).contains(&pixel.r),
+                    "pixel ({}, {}) has red {} after {} passes",
+                    row,
+                    col,
+                    pixel.r,
+                    buffer.passes()
+                );
+            }
+        }
+    }
+}
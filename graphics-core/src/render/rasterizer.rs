@@ -0,0 +1,549 @@
+#[cfg(test)]
+mod tests;
+
+use super::aov::AovBuffers;
+use super::context::RenderContext;
+use super::shading::light_surface;
+use super::stats::Phase;
+use crate::camera::{Camera, Orientable, Renderer};
+use crate::images::{FloatColor, FloatImage};
+use crate::material::Material;
+use crate::space::{scene::Scene, Point, Triangle, Vector};
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// How close to the camera a perspective projection can get before anything
+/// nearer is cut off, since the projection breaks down at the camera itself.
+const NEAR_PLANE: f64 = 1e-6;
+
+/// How the colour is worked out across each triangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadingMode {
+    /// Lights the whole triangle once at its center, giving it a single
+    /// colour.
+    Flat,
+    /// Lights each corner of the triangle using a normal averaged from all of
+    /// the triangles of the object that share that corner, then blends between
+    /// the corners. Curved meshes look smooth instead of faceted.
+    Gouraud
+}
+
+/// Draws the scene by projecting every triangle onto the image and filling in
+/// the pixels whose centers it covers one row at a time, keeping whichever
+/// triangle is closest with a depth buffer. This is much faster than casting
+/// rays, and covers exactly the same pixels as the ray caster does with one
+/// sample per pixel.
+///
+/// Only local lighting is done, so there are no shadows, reflections or
+/// refraction, and the sampling, filter and lens settings are ignored. Only
+/// orthographic and perspective cameras can be rasterized; the other cameras
+/// don't keep straight lines straight, so they fall back on casting rays.
+#[derive(Debug, Clone)]
+pub struct Rasterizer {
+    camera: Camera,
+    shading: ShadingMode
+}
+
+impl Rasterizer {
+    pub fn new(camera: Camera, shading: ShadingMode) -> Self {
+        Rasterizer { camera, shading }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+
+    pub fn shading(&self) -> ShadingMode {
+        self.shading
+    }
+
+    pub fn set_shading(&mut self, shading: ShadingMode) {
+        self.shading = shading;
+    }
+}
+
+/// How points in front of the camera map onto the image.
+#[derive(Debug, Clone, Copy)]
+enum Projection {
+    Orthographic {
+        width: f64,
+        height: f64
+    },
+    Perspective {
+        half_width: f64,
+        half_height: f64,
+        shift: (f64, f64)
+    }
+}
+
+/// Where a camera is and how it projects the scene onto the image, for the
+/// cameras that can be rasterized.
+#[derive(Debug, Clone)]
+pub(super) struct View {
+    location: Point,
+    basis: (Vector, Vector, Vector),
+    projection: Projection
+}
+
+impl View {
+    /// Returns `None` for cameras that don't keep straight lines straight,
+    /// which can't be rasterized.
+    pub(super) fn new(camera: &Camera) -> Option<View> {
+        let projection = match camera {
+            Camera::Ortho(camera) => Projection::Orthographic {
+                width: camera.width(),
+                height: camera.height()
+            },
+            Camera::Perspective(camera) => {
+                let half_height = (camera.fov() / 2.0).tan();
+                Projection::Perspective {
+                    half_width: half_height * camera.aspect_ratio(),
+                    half_height,
+                    shift: camera.shift()
+                }
+            }
+            _ => return None
+        };
+
+        Some(View {
+            location: *camera.location(),
+            basis: camera.orientation().basis(),
+            projection
+        })
+    }
+
+    /// Moves a point into the camera's view, where x is to the right, y is up
+    /// and z is the distance in front of the camera.
+    pub(super) fn to_view(&self, point: &Point) -> Point {
+        let (right, up, forward) = &self.basis;
+        let offset = *point - self.location;
+        Point::new(offset.dot(right), offset.dot(up), offset.dot(forward))
+    }
+
+    /// The direction from the point back towards the camera.
+    pub(super) fn to_viewer(&self, point: &Point) -> Vector {
+        match self.projection {
+            Projection::Orthographic { .. } => self.basis.2 * -1.0,
+            Projection::Perspective { .. } => (self.location - *point).normalized()
+        }
+    }
+
+    /// Whether the triangle should be left out for showing the camera its
+    /// back, following the same rules as the ray caster.
+    pub(super) fn culls(&self, scene: &Scene, triangle: &Triangle, material: &Material) -> bool {
+        scene.render_settings.backface_culling
+            && !material.double_sided()
+            && triangle
+                .normal()
+                .dot(&self.to_viewer(&triangle.geometric_center()))
+                <= 0.0
+    }
+
+    /// Anything in the camera's view closer than this can't be seen.
+    pub(super) fn near(&self) -> f64 {
+        match self.projection {
+            // Orthographic rays start on the camera's plane and only go
+            // forwards
+            Projection::Orthographic { .. } => 0.0,
+            Projection::Perspective { .. } => NEAR_PLANE
+        }
+    }
+
+    /// Where a point in the camera's view lands on an image of the given size,
+    /// in pixels from the top-left corner, along with how much it counts for
+    /// when blending between points (see [`ScreenVertex::weight`]).
+    pub(super) fn project(&self, point: &Point, image_size: (usize, usize)) -> (f64, f64, f64) {
+        let (x, y, weight) = match self.projection {
+            Projection::Orthographic { width, height } => {
+                (point.x / width + 0.5, 0.5 - point.y / height, 1.0)
+            }
+            Projection::Perspective {
+                half_width,
+                half_height,
+                shift: (shift_x, shift_y)
+            } => (
+                (point.x / (point.z * half_width) + 1.0) / 2.0 - shift_x,
+                (1.0 - point.y / (point.z * half_height)) / 2.0 + shift_y,
+                1.0 / point.z
+            )
+        };
+
+        (x * image_size.0 as f64, y * image_size.1 as f64, weight)
+    }
+}
+
+/// A corner of a triangle in the camera's view, where x is to the right, y is
+/// up and z is the distance in front of the camera.
+#[derive(Debug, Clone, Copy)]
+struct ViewVertex {
+    position: Point,
+    color: FloatColor
+}
+
+/// A corner of a triangle once it has been projected onto the image.
+struct ScreenVertex {
+    /// The position in pixels from the top-left corner of the image.
+    x: f64,
+    y: f64,
+    depth: f64,
+    /// How much the corner counts for when blending across the triangle. With
+    /// perspective things further away are squashed up on the image, so this is
+    /// the reciprocal of the depth, which is what changes evenly across the
+    /// image. Orthographic cameras don't squash anything, so it is always one.
+    weight: f64,
+    color: FloatColor
+}
+
+/// The image being drawn, along with how far away the closest thing drawn so
+/// far is for each pixel.
+pub(super) struct Canvas {
+    image_size: (usize, usize),
+    width: usize,
+    /// The pixels that can be drawn in, in image coordinates.
+    area: (usize, usize, usize, usize),
+    /// Where the top-left corner of the canvas is in the full image.
+    offset: (usize, usize),
+    image: FloatImage,
+    depth: Vec<f64>
+}
+
+impl Renderer for Rasterizer {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        let view = match View::new(&self.camera) {
+            Some(view) => view,
+            None => {
+                return self
+                    .camera
+                    .render_hdr_with_context(scene, image_size, context)
+            }
+        };
+
+        let start = Instant::now();
+        let mut canvas = Canvas::new(scene, &self.camera, image_size);
+
+        let total = scene
+            .objects
+            .iter()
+            .map(|obj| obj.mesh.triangles.len())
+            .sum();
+        let mut done = 0;
+        for obj in &scene.objects {
+            let triangles = obj.triangles();
+            let vertex_normals = match self.shading {
+                ShadingMode::Flat => Vec::new(),
+                ShadingMode::Gouraud => smooth_normals(&triangles)
+            };
+
+            for (index, triangle) in triangles.iter().enumerate() {
+                if context.is_cancelled() {
+                    context.add_phase_time(Phase::Rasterizing, start.elapsed());
+                    return canvas.into_image();
+                }
+
+                let material = triangle.material().unwrap_or(obj.base_material());
+                done += 1;
+                if view.culls(scene, triangle, material) {
+                    context.report_progress(done, total, start);
+                    continue;
+                }
+
+                let face_normal = triangle.normal();
+                let center = triangle.geometric_center();
+                // Light the side of the triangle the camera can see
+                let facing = if face_normal.dot(&view.to_viewer(&center)) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+
+                let colors = match self.shading {
+                    ShadingMode::Flat => {
+                        let normal = face_normal * facing;
+                        let color =
+                            light(scene, material, &center, &normal, &view.to_viewer(&center));
+                        [color; 3]
+                    }
+                    ShadingMode::Gouraud => {
+                        let mut colors = [FloatColor::new(0.0, 0.0, 0.0, 0.0); 3];
+                        for (k, point) in triangle.points.iter().enumerate() {
+                            let mut normal = vertex_normals[index][k];
+                            if normal.dot(&face_normal) * facing < 0.0 {
+                                normal = normal * -1.0;
+                            }
+                            colors[k] =
+                                light(scene, material, point, &normal, &view.to_viewer(point));
+                        }
+                        colors
+                    }
+                };
+
+                canvas.draw_triangle(&view, &triangle.points, colors);
+                context.report_progress(done, total, start);
+            }
+        }
+
+        context.add_phase_time(Phase::Rasterizing, start.elapsed());
+        canvas.into_image()
+    }
+
+    /// The AOVs come from casting rays with the rasterizer's camera, since
+    /// they need to be exact.
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        self.camera.render_aovs(scene, image_size)
+    }
+}
+
+impl Canvas {
+    /// A canvas showing just the background as seen by the camera, sized and
+    /// placed for the render region in the scene's render settings.
+    pub(super) fn new(scene: &Scene, camera: &Camera, image_size: (usize, usize)) -> Canvas {
+        let settings = &scene.render_settings;
+        let area = settings.render_area(image_size);
+        let cropped = matches!(&settings.region, Some(region) if region.crop);
+        let (width, height, offset) = if cropped {
+            (area.width, area.height, (area.x, area.y))
+        } else {
+            (image_size.0, image_size.1, (0, 0))
+        };
+
+        let mut image = FloatImage::new(width, height);
+        for j in area.y..(area.y + area.height) {
+            for i in area.x..(area.x + area.width) {
+                let x = (i as f64 + 0.5) / (image_size.0 as f64);
+                let y = (j as f64 + 0.5) / (image_size.1 as f64);
+                let ray = camera.primary_ray(x, y);
+                let background = scene
+                    .background
+                    .color_in_direction(ray.direction(), &scene.render_settings.tone_mapping);
+                image.set_pixel(background, j - offset.1, i - offset.0);
+            }
+        }
+
+        Canvas {
+            image_size,
+            width,
+            area: (area.x, area.y, area.width, area.height),
+            offset,
+            image,
+            depth: vec![f64::INFINITY; width * height]
+        }
+    }
+
+    pub(super) fn image(&self) -> &FloatImage {
+        &self.image
+    }
+
+    pub(super) fn into_image(self) -> FloatImage {
+        self.image
+    }
+
+    /// How far away the closest thing drawn at the pixel is, from the top-left
+    /// corner of the full image. This is infinite where nothing has been drawn,
+    /// including outside the render region.
+    pub(super) fn depth_at(&self, i: usize, j: usize) -> f64 {
+        let (area_x, area_y, area_width, area_height) = self.area;
+        if i < area_x || j < area_y || i >= area_x + area_width || j >= area_y + area_height {
+            return f64::INFINITY;
+        }
+
+        self.depth[(j - self.offset.1) * self.width + (i - self.offset.0)]
+    }
+
+    /// Draws a triangle in the world with the given colours at its corners,
+    /// seen from the view.
+    pub(super) fn draw_triangle(
+        &mut self,
+        view: &View,
+        points: &[Point; 3],
+        colors: [FloatColor; 3]
+    ) {
+        let vertices = [0, 1, 2].map(|k| ViewVertex {
+            position: view.to_view(&points[k]),
+            color: colors[k]
+        });
+
+        let clipped = clip_near(&vertices, view.near());
+        for k in 1..clipped.len().saturating_sub(1) {
+            let screen = [&clipped[0], &clipped[k], &clipped[k + 1]].map(|v| self.project(view, v));
+            self.fill_triangle(&screen);
+        }
+    }
+
+    fn project(&self, view: &View, vertex: &ViewVertex) -> ScreenVertex {
+        let (x, y, weight) = view.project(&vertex.position, self.image_size);
+        ScreenVertex {
+            x,
+            y,
+            depth: vertex.position.z,
+            weight,
+            color: vertex.color
+        }
+    }
+
+    /// Fills in every pixel whose center is inside the triangle and closer than
+    /// what is already there. Each row of pixels only goes across the span
+    /// between where the triangle's edges cross it.
+    fn fill_triangle(&mut self, vertices: &[ScreenVertex; 3]) {
+        let [a, b, c] = vertices;
+        let area = edge(a, b, c.x, c.y);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+
+        let (area_x, area_y, area_width, area_height) = self.area;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(area_y as f64) as usize;
+        let max_y =
+            a.y.max(b.y)
+                .max(c.y)
+                .ceil()
+                .min((area_y + area_height) as f64) as usize;
+
+        for j in min_y..max_y {
+            let y = j as f64 + 0.5;
+            let (left, right) = match row_span(vertices, y) {
+                Some(span) => span,
+                None => continue
+            };
+            // Pixels right on the ends of the span are left to the edge test
+            // below, so that they are decided exactly like the ray caster does
+            let min_x = (left - 0.5).floor().max(area_x as f64) as usize;
+            let max_x = ((right - 0.5).ceil() + 1.0).min((area_x + area_width) as f64) as usize;
+
+            for i in min_x..max_x {
+                let x = i as f64 + 0.5;
+                // How much of each corner there is at this pixel, which are
+                // all positive inside the triangle
+                let weights = [
+                    edge(b, c, x, y) / area,
+                    edge(c, a, x, y) / area,
+                    edge(a, b, x, y) / area
+                ];
+                if weights.iter().any(|w| *w < 0.0) {
+                    continue;
+                }
+
+                let weights = [0, 1, 2].map(|k| weights[k] * vertices[k].weight);
+                let total_weight: f64 = weights.iter().sum();
+                let depth: f64 =
+                    (0..3).map(|k| weights[k] * vertices[k].depth).sum::<f64>() / total_weight;
+                let index = (j - self.offset.1) * self.width + (i - self.offset.0);
+                if depth >= self.depth[index] {
+                    continue;
+                }
+
+                let mut color = FloatColor::new(0.0, 0.0, 0.0, 0.0);
+                for k in 0..3 {
+                    color += vertices[k].color * (weights[k] / total_weight);
+                }
+
+                self.depth[index] = depth;
+                self.image
+                    .set_pixel(color, j - self.offset.1, i - self.offset.0);
+            }
+        }
+    }
+}
+
+/// The lighting at a point, without shadows.
+fn light(
+    scene: &Scene,
+    material: &Material,
+    point: &Point,
+    normal: &Vector,
+    to_viewer: &Vector
+) -> FloatColor {
+    light_surface(scene, material, point, normal, to_viewer, None)
+}
+
+/// Where the edges of the triangle cross the row at `y`, from left to right, or
+/// `None` if the row misses the triangle.
+fn row_span(vertices: &[ScreenVertex; 3], y: f64) -> Option<(f64, f64)> {
+    let mut span: Option<(f64, f64)> = None;
+    for k in 0..3 {
+        let (p, q) = (&vertices[k], &vertices[(k + 1) % 3]);
+        if p.y == q.y || y < p.y.min(q.y) || y > p.y.max(q.y) {
+            continue;
+        }
+
+        let x = p.x + (y - p.y) * (q.x - p.x) / (q.y - p.y);
+        span = Some(match span {
+            Some((left, right)) => (left.min(x), right.max(x)),
+            None => (x, x)
+        });
+    }
+
+    span
+}
+
+/// Twice the signed area of the triangle made by the edge from `a` to `b` and
+/// the point. This is positive on one side of the edge and negative on the
+/// other.
+fn edge(a: &ScreenVertex, b: &ScreenVertex, x: f64, y: f64) -> f64 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+/// Cuts off the parts of the triangle closer to the camera than `near`. What
+/// is left has up to four corners.
+fn clip_near(vertices: &[ViewVertex; 3], near: f64) -> Vec<ViewVertex> {
+    let mut clipped = Vec::with_capacity(4);
+    for k in 0..3 {
+        let current = &vertices[k];
+        let next = &vertices[(k + 1) % 3];
+        let current_inside = current.position.z >= near;
+        let next_inside = next.position.z >= near;
+
+        if current_inside {
+            clipped.push(*current);
+        }
+        if current_inside != next_inside {
+            let t = (near - current.position.z) / (next.position.z - current.position.z);
+            clipped.push(ViewVertex {
+                position: current.position + (next.position - current.position) * t,
+                color: current.color * (1.0 - t) + next.color * t
+            });
+        }
+    }
+
+    clipped
+}
+
+/// Works out a normal for every corner of every triangle by adding up the
+/// normals of all the triangles that share that corner, weighted by their area.
+fn smooth_normals(triangles: &[Triangle]) -> Vec<[Vector; 3]> {
+    let key = |point: &Point| [point.x.to_bits(), point.y.to_bits(), point.z.to_bits()];
+
+    let mut sums: HashMap<[u64; 3], Vector> = HashMap::new();
+    for triangle in triangles {
+        let [a, b, c] = &triangle.points;
+        // The length of the cross product is twice the triangle's area
+        let weighted = (*b - *a).cross(&(*c - *a));
+        for point in &triangle.points {
+            let sum = sums.entry(key(point)).or_insert(Point::new(0.0, 0.0, 0.0));
+            *sum = *sum + weighted;
+        }
+    }
+
+    triangles
+        .iter()
+        .map(|triangle| {
+            triangle.points.map(|point| {
+                let sum = sums[&key(&point)];
+                if sum.length() > 0.0 {
+                    sum.normalized()
+                } else {
+                    triangle.normal()
+                }
+            })
+        })
+        .collect()
+}
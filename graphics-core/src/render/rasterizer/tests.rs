@@ -0,0 +1,266 @@
+use super::*;
+use crate::camera::{EquirectangularCamera, OrthographicCamera, PerspectiveCamera};
+use crate::images::{Color, WHITE};
+use crate::render::RenderRegion;
+use crate::space::lighting::{DirectionalLight, PointLight};
+use crate::space::object::{Mesh, Object};
+use crate::test_support::unlit_scene;
+
+/// A tangle of overlapping triangles at different depths, some of them
+/// reaching behind the orthographic camera used below.
+fn overlapping_triangles() -> Object {
+    let mut triangles = Vec::new();
+    for i in 0..12 {
+        let offset = (i as f64) * 0.3 - 1.8;
+        let mut triangle = Triangle::new([
+            Point::new(offset, 1.0, 3.0 + offset),
+            Point::new(offset + 1.0, -1.2, 5.0),
+            Point::new(-offset, -0.5, 6.0 - offset * 2.0)
+        ]);
+        triangle.set_material(Material::new(Color::new(
+            20 * i as u8,
+            255 - 20 * i as u8,
+            90,
+            255
+        )));
+        triangles.push(triangle);
+    }
+
+    Object::new(Mesh::new(triangles))
+}
+
+/// A scene where surfaces show their colour exactly, so the ray caster and the
+/// rasterizer should agree.
+fn overlapping_scene() -> Scene {
+    let mut scene = unlit_scene();
+    scene.add_object(overlapping_triangles());
+    scene
+}
+
+/// Part of a sphere around the origin made of triangles that share corners.
+fn faceted_dome() -> Object {
+    let point = |lat: f64, lon: f64| {
+        Point::new(lat.cos() * lon.sin(), lat.sin(), -lat.cos() * lon.cos()) * 2.0
+    };
+
+    let steps = 6;
+    let step = 0.25;
+    let mut triangles = Vec::new();
+    for a in 0..steps {
+        for b in 0..steps {
+            let lat = (a as f64 - steps as f64 / 2.0) * step;
+            let lon = (b as f64 - steps as f64 / 2.0) * step;
+            let corners = [
+                point(lat, lon),
+                point(lat + step, lon),
+                point(lat + step, lon + step),
+                point(lat, lon + step)
+            ];
+            for points in [
+                [corners[0], corners[1], corners[2]],
+                [corners[0], corners[2], corners[3]]
+            ] {
+                let mut triangle = Triangle::new(points);
+                triangle.set_material(Material::new(WHITE));
+                triangles.push(triangle);
+            }
+        }
+    }
+
+    Object::new(Mesh::new(triangles))
+}
+
+#[test]
+fn coverage_matches_the_ray_caster() {
+    let scene = overlapping_scene();
+    let mut ortho = OrthographicCamera::new(Point::new(0.0, 0.0, 2.0), 4.0, 3.0);
+    ortho.set_orientation(crate::camera::Orientation::new(
+        Point::new(0.1, 0.0, 1.0),
+        Point::new(0.0, 1.0, 0.0),
+        0.2
+    ));
+    let mut shifted = PerspectiveCamera::new_default();
+    shifted.set_shift((0.2, -0.1));
+
+    for camera in [
+        Camera::new_default(),
+        Camera::Ortho(ortho),
+        Camera::Perspective(shifted)
+    ] {
+        let cast = camera.render(&scene, (64, 48));
+        let rasterized = Rasterizer::new(camera, ShadingMode::Flat).render(&scene, (64, 48));
+
+        assert_eq!(cast.get_image_data(), rasterized.get_image_data());
+    }
+}
+
+#[test]
+fn slivers_are_covered_like_the_ray_caster() {
+    // Long thin triangles at all sorts of angles, which many rows of pixels
+    // only just clip. Their corners are kept off of the pixel centers, since
+    // a corner right on one is a tie either way could take
+    let mut triangles = Vec::new();
+    for i in 0..16 {
+        let angle = i as f64 * 0.4;
+        let (dx, dy) = (angle.cos(), angle.sin());
+        // Each at its own depth, so there are no ties over which is in front
+        let start = Point::new(
+            -0.93 + 0.1 * (i % 4) as f64,
+            -0.72 + 0.1 * (i / 4) as f64,
+            2.0 + 0.1 * i as f64
+        );
+        let mut triangle = Triangle::new([
+            start,
+            start + Point::new(dx, dy, 0.0) * 1.3,
+            start + Point::new(dx - dy * 0.03, dy + dx * 0.03, 0.0) * 1.3
+        ]);
+        triangle.set_material(Material::new(Color::new(
+            40 + 12 * i as u8,
+            200,
+            255 - 12 * i as u8,
+            255
+        )));
+        triangles.push(triangle);
+    }
+    let mut scene = unlit_scene();
+    scene.add_object(Object::new(Mesh::new(triangles)));
+
+    let camera = Camera::Ortho(OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0));
+    let cast = camera.render(&scene, (20, 20));
+    let rasterized = Rasterizer::new(camera, ShadingMode::Flat).render(&scene, (20, 20));
+
+    assert_eq!(cast.get_image_data(), rasterized.get_image_data());
+}
+
+#[test]
+fn flat_shading_matches_the_ray_caster_without_shadows() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.1;
+    let mut triangle = Triangle::new([
+        Point::new(-1.0, -1.0, 3.0),
+        Point::new(0.0, 1.0, 4.0),
+        Point::new(1.0, -1.0, 3.0)
+    ]);
+    triangle.set_material(Material::new(Color::new(200, 100, 50, 255)));
+    scene.add_object(Object::new(Mesh::new(vec![triangle])));
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.2, -1.0, 1.0),
+        WHITE,
+        0.8
+    )));
+
+    // With a directional light the lighting is the same all over the triangle
+    let cast = Camera::new_default().render(&scene, (9, 9));
+    let rasterized =
+        Rasterizer::new(Camera::new_default(), ShadingMode::Flat).render(&scene, (9, 9));
+
+    assert_eq!(cast.get_pixel(5, 4).color, rasterized.get_pixel(5, 4).color);
+}
+
+#[test]
+fn gouraud_shading_blends_across_triangles() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.add_object(faceted_dome());
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(0.0, 0.0, 0.0),
+        WHITE,
+        4.0
+    )));
+
+    let camera = Camera::Perspective(PerspectiveCamera::new(
+        Point::new(0.0, 0.0, 1.0),
+        Point::new(0.0, 0.0, -1.0),
+        Point::new(0.0, 1.0, 0.0),
+        1.2,
+        1.0
+    ));
+
+    // Count how many different shades there are along a row
+    let shades = |shading: ShadingMode| {
+        let image = Rasterizer::new(camera.clone(), shading).render(&scene, (40, 40));
+        let mut levels: Vec<u8> = (0..40)
+            .map(|col| image.get_pixel(20, col).color.r)
+            .collect();
+        levels.dedup();
+        levels.len()
+    };
+
+    assert!(shades(ShadingMode::Gouraud) > shades(ShadingMode::Flat));
+}
+
+#[test]
+fn nearer_triangles_hide_further_ones() {
+    let mut scene = unlit_scene();
+    for (z, color) in [
+        (5.0, Color::new(0, 0, 255, 255)),
+        (3.0, Color::new(255, 0, 0, 255))
+    ] {
+        let mut triangle = Triangle::new([
+            Point::new(-1.0, -1.0, z),
+            Point::new(0.0, 1.0, z),
+            Point::new(1.0, -1.0, z)
+        ]);
+        triangle.set_material(Material::new(color));
+        scene.add_object(Object::new(Mesh::new(vec![triangle])));
+    }
+
+    // Drawing the near triangle first and the far one after shouldn't matter
+    scene.objects.reverse();
+    let image = Rasterizer::new(Camera::new_default(), ShadingMode::Flat).render(&scene, (9, 9));
+
+    assert_eq!(Color::new(255, 0, 0, 255), image.get_pixel(5, 4).color);
+}
+
+#[test]
+fn regions_are_respected() {
+    let mut scene = overlapping_scene();
+    let rasterizer = Rasterizer::new(Camera::new_default(), ShadingMode::Flat);
+    let full = rasterizer.render_hdr(&scene, (30, 20));
+
+    scene.render_settings.region = Some(RenderRegion::new(5, 4, 10, 8, true));
+    let cropped = rasterizer.render_hdr(&scene, (30, 20));
+    assert_eq!(10, cropped.get_width());
+    assert_eq!(8, cropped.get_height());
+    for j in 0..8 {
+        for i in 0..10 {
+            assert_eq!(full.get_pixel(j + 4, i + 5), cropped.get_pixel(j, i));
+        }
+    }
+
+    scene.render_settings.region = Some(RenderRegion::new(5, 4, 10, 8, false));
+    let placed = rasterizer.render_hdr(&scene, (30, 20));
+    assert_eq!(0.0, placed.get_pixel(0, 0).a);
+    assert_eq!(full.get_pixel(6, 7), placed.get_pixel(6, 7));
+}
+
+#[test]
+fn other_cameras_fall_back_to_casting_rays() {
+    let scene = overlapping_scene();
+    let camera = Camera::Equirectangular(EquirectangularCamera::new_default());
+
+    let cast = camera.render(&scene, (20, 10));
+    let rasterized = Rasterizer::new(camera, ShadingMode::Gouraud).render(&scene, (20, 10));
+
+    assert_eq!(cast.get_image_data(), rasterized.get_image_data());
+}
+
+#[test]
+fn back_faces_are_culled_like_the_ray_caster() {
+    let mut scene = overlapping_scene();
+    let camera = Camera::new_default();
+    let rasterizer = Rasterizer::new(camera.clone(), ShadingMode::Flat);
+    let unculled = rasterizer.render(&scene, (64, 48));
+
+    scene.render_settings.backface_culling = true;
+    for triangle in &mut scene.objects[0].mesh.triangles {
+        let mut material = triangle.material().unwrap().clone();
+        material.set_double_sided(false);
+        triangle.set_material(material);
+    }
+    let cast = camera.render(&scene, (64, 48));
+    let rasterized = rasterizer.render(&scene, (64, 48));
+
+    assert_eq!(cast.get_image_data(), rasterized.get_image_data());
+    assert_ne!(unculled.get_image_data(), rasterized.get_image_data());
+}
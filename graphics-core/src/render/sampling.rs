@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests;
+
+use rand::Rng;
+
+/// Where the samples for a pixel are taken. Both patterns take N×N samples
+/// spread over the area the filter covers, so taking more samples smooths out
+/// jagged edges at the cost of casting more rays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Samples lined up evenly in an N×N grid. One sample means casting a
+    /// single ray through the center of the pixel.
+    Grid(usize),
+    /// Samples placed randomly inside each cell of an N×N grid. This trades
+    /// the regular patterns a grid can leave on fine details for noise.
+    Jittered(usize)
+}
+
+impl Sampling {
+    /// How many samples are taken along each side of the grid.
+    pub fn grid_size(&self) -> usize {
+        match self {
+            Sampling::Grid(size) | Sampling::Jittered(size) => (*size).max(1)
+        }
+    }
+
+    /// The positions of the samples relative to the center of the pixel,
+    /// measured in pixels. They cover a square reaching `radius` out from the
+    /// center on each side.
+    pub fn offsets<R: Rng>(&self, radius: f64, rng: &mut R) -> Vec<(f64, f64)> {
+        let size = self.grid_size();
+        let cell = 2.0 * radius / size as f64;
+        let mut offsets = Vec::with_capacity(size * size);
+
+        for row in 0..size {
+            for column in 0..size {
+                let (x, y) = match self {
+                    Sampling::Grid(_) => (0.5, 0.5),
+                    Sampling::Jittered(_) => (rng.gen(), rng.gen())
+                };
+                offsets.push((
+                    (column as f64 + x) * cell - radius,
+                    (row as f64 + y) * cell - radius
+                ));
+            }
+        }
+
+        offsets
+    }
+}
+
+/// How much each sample counts towards the final colour of a pixel, based on
+/// how far away from the center of the pixel it is. Filters that reach past
+/// the edge of the pixel blend it a little with its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Every sample inside the pixel counts the same. Sharp, but not as good
+    /// at hiding jagged edges.
+    Box,
+    /// Samples count less the further they are from the center, reaching zero
+    /// one pixel away.
+    Tent,
+    /// A bell curve reaching out one and a half pixels. Smooth, but a little
+    /// blurry.
+    Gaussian,
+    /// The Mitchell-Netravali filter, reaching out two pixels. Its negative
+    /// lobes keep edges sharper than the Gaussian.
+    Mitchell
+}
+
+impl Filter {
+    /// How far in pixels from the center of a pixel samples still count.
+    pub fn radius(&self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0
+        }
+    }
+
+    /// The weight of a sample at the given offset in pixels from the center of
+    /// the pixel. This can be negative for the Mitchell filter.
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - x,
+            Filter::Gaussian => {
+                // Shifted down so the curve reaches zero at the edge
+                let gaussian = |x: f64| (-2.0 * x * x).exp();
+                gaussian(x) - gaussian(self.radius())
+            }
+            Filter::Mitchell => mitchell(x)
+        }
+    }
+}
+
+/// The Mitchell-Netravali cubic with B and C both set to one third, which is
+/// what its authors recommended.
+fn mitchell(x: f64) -> f64 {
+    const B: f64 = 1.0 / 3.0;
+    const C: f64 = 1.0 / 3.0;
+
+    let x2 = x * x;
+    let x3 = x2 * x;
+    if x < 1.0 {
+        ((12.0 - 9.0 * B - 6.0 * C) * x3 + (-18.0 + 12.0 * B + 6.0 * C) * x2 + (6.0 - 2.0 * B))
+            / 6.0
+    } else {
+        ((-B - 6.0 * C) * x3
+            + (6.0 * B + 30.0 * C) * x2
+            + (-12.0 * B - 48.0 * C) * x
+            + (8.0 * B + 24.0 * C))
+            / 6.0
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn single_grid_sample_is_the_pixel_center() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    assert_eq!(vec![(0.0, 0.0)], Sampling::Grid(1).offsets(0.5, &mut rng));
+    assert_eq!(vec![(0.0, 0.0)], Sampling::Grid(0).offsets(2.0, &mut rng));
+}
+
+#[test]
+fn grid_samples_are_evenly_spaced() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let offsets = Sampling::Grid(2).offsets(0.5, &mut rng);
+
+    assert_eq!(
+        vec![(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)],
+        offsets
+    );
+}
+
+#[test]
+fn jittered_samples_stay_in_their_cells() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let offsets = Sampling::Jittered(4).offsets(1.0, &mut rng);
+    assert_eq!(16, offsets.len());
+
+    for (index, (x, y)) in offsets.into_iter().enumerate() {
+        let (row, column) = (index / 4, index % 4);
+        let cell_x = -1.0 + column as f64 * 0.5;
+        let cell_y = -1.0 + row as f64 * 0.5;
+
+        assert!(x >= cell_x && x < cell_x + 0.5);
+        assert!(y >= cell_y && y < cell_y + 0.5);
+    }
+}
+
+#[test]
+fn filters_are_strongest_in_the_center_and_stop_at_their_radius() {
+    for filter in [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell
+    ] {
+        let center = filter.weight(0.0, 0.0);
+        assert!(center > 0.0);
+        assert!(filter.weight(0.3, 0.1) <= center);
+        assert_eq!(0.0, filter.weight(filter.radius() + 0.01, 0.0));
+        assert_eq!(0.0, filter.weight(0.0, -filter.radius() - 0.01));
+    }
+
+    assert_eq!(1.0, Filter::Box.weight(0.4, -0.4));
+    assert_eq!(0.25, Filter::Tent.weight(0.5, 0.5));
+}
+
+#[test]
+fn mitchell_filter_has_negative_lobes() {
+    assert!(Filter::Mitchell.weight(1.5, 0.0) < 0.0);
+    // The two pieces of the curve meet at one pixel away
+    assert!((mitchell(1.0 - 1e-9) - mitchell(1.0)).abs() < 1e-6);
+}
@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests;
+
+use super::stats;
+use crate::collision::{
+    bvh::{Bvh, Hit},
+    Ray
+};
+use crate::images::FloatColor;
+use crate::material::Material;
+use crate::space::{scene::Scene, Point, Vector};
+
+/// Finds the colour seen along the ray. Reflective surfaces send out another
+/// ray in the mirror direction, and transparent ones send one through the
+/// surface bent by Snell's law. What those rays see is blended with the
+/// surface's own colour, going up to the maximum depth in the render settings.
+pub(crate) fn trace(scene: &Scene, bvh: &Bvh, ray: &Ray, depth: usize) -> FloatColor {
+    match bvh.closest_hit(ray) {
+        Some(hit) => trace_hit(scene, bvh, &hit, ray, depth),
+        None => scene
+            .background
+            .color_in_direction(ray.direction(), &scene.render_settings.tone_mapping)
+    }
+}
+
+/// Like [`trace`], for when the ray has already been found to hit the scene.
+pub(crate) fn trace_hit(
+    scene: &Scene,
+    bvh: &Bvh,
+    hit: &Hit,
+    ray: &Ray,
+    depth: usize
+) -> FloatColor {
+    let local = shade(scene, bvh, hit, ray);
+    let material = hit.triangle.material();
+    let reflectivity = material.reflectivity();
+    let transmission = material.transmission();
+    if (reflectivity <= 0.0 && transmission <= 0.0) || depth >= scene.render_settings.max_depth {
+        return local;
+    }
+
+    let bias = scene.render_settings.ray_bias;
+    let normal = facing_normal(hit, ray);
+    let direction = *ray.direction();
+    let point = hit.collision.point;
+
+    let reflected_direction = direction - normal * (2.0 * direction.dot(&normal));
+    let reflected_ray = Ray::new(point + normal * bias, reflected_direction);
+    stats::count(|counts| counts.secondary_rays += 1);
+    let reflected = trace(scene, bvh, &reflected_ray, depth + 1);
+
+    let mut result = local * (1.0 - reflectivity) + reflected * reflectivity;
+    if transmission > 0.0 {
+        // Normals point out of objects, so a ray going the same way as the
+        // triangle's own normal must be on its way out.
+        let entering = hit.triangle.triangle.normal().dot(&direction) < 0.0;
+        let ior = material.index_of_refraction();
+        let (n1, n2) = if entering { (1.0, ior) } else { (ior, 1.0) };
+
+        let see_through = match refract(&direction, &normal, n1 / n2) {
+            // Total internal reflection, so none of the light gets through
+            None => reflected,
+            Some((refracted_direction, cos_t)) => {
+                let cos_i = -direction.dot(&normal);
+                let fresnel = schlick(cos_i, cos_t, n1, n2);
+
+                let refracted_ray = Ray::new(point - normal * bias, refracted_direction);
+                stats::count(|counts| counts.secondary_rays += 1);
+                // Light passing through the surface is tinted by its colour
+                let refracted = trace(scene, bvh, &refracted_ray, depth + 1)
+                    * scene
+                        .render_settings
+                        .tone_mapping
+                        .linear_color(material.color());
+
+                reflected * fresnel + refracted * (1.0 - fresnel)
+            }
+        };
+        result = result * (1.0 - transmission) + see_through * transmission;
+    }
+
+    result.a = local.a;
+    result
+}
+
+/// Bends a direction as it passes through a surface, where `eta` is the index
+/// of refraction being left divided by the one being entered. Returns the new
+/// direction along with the cosine of its angle to the surface, or `None` when
+/// the angle is so shallow that all of the light is reflected instead.
+pub(super) fn refract(direction: &Vector, normal: &Vector, eta: f64) -> Option<(Vector, f64)> {
+    let cos_i = -direction.dot(normal);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((*direction * eta + *normal * (eta * cos_i - cos_t), cos_t))
+}
+
+/// Schlick's approximation of the Fresnel equations, giving how much light is
+/// reflected off of the surface rather than passing through it.
+pub(super) fn schlick(cos_i: f64, cos_t: f64, n1: f64, n2: f64) -> f64 {
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    // The angle on the less dense side is the one that matters
+    let cos = if n1 > n2 { cos_t } else { cos_i };
+
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
+
+/// The normal of the triangle that was hit, flipped if needed so that it
+/// points back out towards where the ray came from. Triangles can be seen from
+/// both sides, so this is the side of the surface that matters.
+pub(super) fn facing_normal(hit: &Hit, ray: &Ray) -> Vector {
+    let normal = hit.triangle.triangle.normal();
+    if normal.dot(ray.direction()) > 0.0 {
+        normal * -1.0
+    } else {
+        normal
+    }
+}
+
+/// Works out the colour of the surface at the hit, with shadows from anything
+/// else in the scene.
+fn shade(scene: &Scene, bvh: &Bvh, hit: &Hit, ray: &Ray) -> FloatColor {
+    let to_viewer = *ray.direction() * -1.0;
+    let normal = facing_normal(hit, ray);
+
+    light_surface(
+        scene,
+        hit.triangle.material(),
+        &hit.collision.point,
+        &normal,
+        &to_viewer,
+        Some(bvh)
+    )
+}
+
+/// Works out the colour of a point on a surface using the Blinn-Phong model.
+/// Each light in the scene adds Lambertian diffuse light plus a specular
+/// highlight, and the ambient setting stops unlit areas from being pure black.
+/// When there is a BVH to check against, lights that are blocked by something
+/// in it are skipped, which is what creates shadows.
+pub(super) fn light_surface(
+    scene: &Scene,
+    material: &Material,
+    point: &Point,
+    normal: &Vector,
+    to_viewer: &Vector,
+    shadows: Option<&Bvh>
+) -> FloatColor {
+    let base = scene
+        .render_settings
+        .tone_mapping
+        .linear_color(material.color());
+
+    let mut result = base * scene.render_settings.ambient;
+    for light in &scene.lights {
+        let illumination = match light.illumination_at(point) {
+            Some(illumination) => illumination,
+            None => continue
+        };
+
+        let diffuse = normal.dot(&illumination.direction);
+        if diffuse <= 0.0 {
+            continue;
+        }
+
+        if let Some(bvh) = shadows {
+            // Start the shadow ray slightly off the surface so it doesn't hit
+            // the triangle it is starting on.
+            let bias = scene.render_settings.ray_bias;
+            let shadow_ray = Ray::new(*point + *normal * bias, illumination.direction);
+            stats::count(|counts| counts.shadow_rays += 1);
+            if bvh.any_hit(&shadow_ray, illumination.distance - bias) {
+                continue;
+            }
+        }
+
+        let light_color = scene
+            .render_settings
+            .tone_mapping
+            .linear_color(&illumination.color)
+            * illumination.strength;
+        result += base * light_color * diffuse;
+
+        let halfway = (illumination.direction + *to_viewer).normalized();
+        let specular = normal.dot(&halfway).max(0.0).powf(material.shininess());
+        result += light_color * (material.specular() * specular);
+    }
+
+    // Lighting shouldn't change how see through the surface is
+    result.a = base.a;
+    result
+}
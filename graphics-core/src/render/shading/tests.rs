@@ -0,0 +1,406 @@
+use super::*;
+use crate::collision::bvh::SceneTriangle;
+use crate::collision::{Collision, Ray};
+use crate::images::{Color, WHITE};
+use crate::material::Material;
+use crate::space::background::Background;
+use crate::space::lighting::{DirectionalLight, PointLight};
+use crate::space::object::{Mesh, Object};
+use crate::space::{Point, Triangle, ORIGIN};
+use crate::test_support::unlit_scene;
+
+/// A triangle facing the camera at the origin, one unit away.
+fn facing_triangle(material: Material) -> SceneTriangle {
+    let triangle = Triangle::new([
+        Point::new(0.0, 1.0, 1.0),
+        Point::new(1.0, -1.0, 1.0),
+        Point::new(-1.0, -1.0, 1.0)
+    ]);
+
+    SceneTriangle::new(triangle, 0, 0, material)
+}
+
+fn shade_center(scene: &Scene, triangle: &SceneTriangle) -> FloatColor {
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+    let hit = Hit {
+        collision: Collision::new(Point::new(0.0, 0.0, 1.0), 1.0, (0.25, 0.25)),
+        triangle
+    };
+
+    shade(scene, &Bvh::from_scene(scene), &hit, &ray)
+}
+
+fn assert_close(expected: f64, actual: f64) {
+    assert!(
+        (expected - actual).abs() < 1e-9,
+        "expected {} but got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn unlit_surfaces_only_get_ambient_light() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.25;
+    let triangle = facing_triangle(Material::new(WHITE));
+
+    let color = shade_center(&scene, &triangle);
+    assert_close(0.25, color.r);
+    assert_close(0.25, color.g);
+    assert_close(0.25, color.b);
+    assert_close(1.0, color.a);
+}
+
+#[test]
+fn diffuse_light_depends_on_angle() {
+    let triangle = facing_triangle(Material::new(Color::new(255, 0, 0, 255)));
+
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, 0.0, 1.0),
+        WHITE,
+        1.0
+    )));
+    let head_on = shade_center(&scene, &triangle);
+    assert_close(1.0, head_on.r);
+    assert_close(0.0, head_on.g);
+
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, -1.0, 1.0),
+        WHITE,
+        1.0
+    )));
+    let angled = shade_center(&scene, &triangle);
+    assert_close(std::f64::consts::FRAC_1_SQRT_2, angled.r);
+}
+
+#[test]
+fn lights_behind_the_surface_do_nothing() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(0.0, 0.0, 5.0),
+        WHITE,
+        100.0
+    )));
+    let triangle = facing_triangle(Material::new(WHITE));
+
+    let color = shade_center(&scene, &triangle);
+    assert_close(0.0, color.r);
+}
+
+#[test]
+fn specular_highlight_adds_light_colour() {
+    let mut material = Material::new(Color::new(0, 0, 255, 255));
+    material.set_specular(0.5);
+    let triangle = facing_triangle(material);
+
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, 0.0, 1.0),
+        WHITE,
+        1.0
+    )));
+
+    // The light, viewer, and normal all line up so the highlight is at full
+    // strength and shows up even in channels the surface has none of.
+    let color = shade_center(&scene, &triangle);
+    assert_close(0.5, color.r);
+    assert_close(0.5, color.g);
+    assert_close(1.5, color.b);
+}
+
+#[test]
+fn triangles_are_lit_from_either_side() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, 0.0, 1.0),
+        WHITE,
+        1.0
+    )));
+
+    let front = facing_triangle(Material::new(WHITE));
+    let mut back = front.clone();
+    back.triangle.points.swap(1, 2);
+
+    assert_close(1.0, shade_center(&scene, &front).r);
+    assert_close(1.0, shade_center(&scene, &back).r);
+}
+
+#[test]
+fn blocked_lights_cast_shadows() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.1;
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(0.0, 0.0, -4.0),
+        WHITE,
+        25.0
+    )));
+    let triangle = facing_triangle(Material::new(WHITE));
+
+    let lit = shade_center(&scene, &triangle);
+    assert_close(1.1, lit.r);
+
+    // Put something between the light and the surface
+    scene.add_object(Object::new(Mesh::new(vec![Triangle::new([
+        Point::new(0.0, 1.0, -2.0),
+        Point::new(1.0, -1.0, -2.0),
+        Point::new(-1.0, -1.0, -2.0)
+    ])])));
+    let shadowed = shade_center(&scene, &triangle);
+    assert_close(0.1, shadowed.r);
+}
+
+#[test]
+fn objects_behind_the_light_do_not_cast_shadows() {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(0.0, 0.0, -1.0),
+        WHITE,
+        4.0
+    )));
+    scene.add_object(Object::new(Mesh::new(vec![Triangle::new([
+        Point::new(0.0, 1.0, -3.0),
+        Point::new(1.0, -1.0, -3.0),
+        Point::new(-1.0, -1.0, -3.0)
+    ])])));
+    let triangle = facing_triangle(Material::new(WHITE));
+
+    assert_close(1.0, shade_center(&scene, &triangle).r);
+}
+
+#[test]
+fn surfaces_do_not_shadow_themselves() {
+    // Render a lit floor at a shallow angle, where rounding errors would make
+    // shadow rays hit the floor itself without any bias.
+    let floor = Object::new(Mesh::new(vec![Triangle::new([
+        Point::new(-50.0, -1.0, 0.0),
+        Point::new(50.0, -1.0, 0.0),
+        Point::new(0.0, -1.0, 100.0)
+    ])]));
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 0.0;
+    scene.add_object(floor);
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.3, -1.0, 0.2),
+        WHITE,
+        1.0
+    )));
+
+    let bvh = Bvh::from_scene(&scene);
+    for i in 0..200 {
+        let x = (i as f64) / 20.0 - 5.0;
+        let ray = Ray::new(ORIGIN, Point::new(x, -0.3, 1.0));
+        let hit = bvh.closest_hit(&ray).unwrap();
+        let color = shade(&scene, &bvh, &hit, &ray);
+        assert!(color.r > 0.5, "the floor was shadowed by itself");
+    }
+}
+
+/// A scene with a mirror one unit in front of the origin and a red wall two
+/// units behind it, lit only by ambient light.
+fn mirror_scene(reflectivity: f64) -> Scene {
+    let mut mirror_material = Material::new(WHITE);
+    mirror_material.set_reflectivity(reflectivity);
+    let mut mirror = Triangle::new([
+        Point::new(0.0, 1.0, 1.0),
+        Point::new(1.0, -1.0, 1.0),
+        Point::new(-1.0, -1.0, 1.0)
+    ]);
+    mirror.set_material(mirror_material);
+
+    let mut wall = Triangle::new([
+        Point::new(0.0, 1.0, -2.0),
+        Point::new(1.0, -1.0, -2.0),
+        Point::new(-1.0, -1.0, -2.0)
+    ]);
+    wall.set_material(Material::new(Color::new(255, 0, 0, 255)));
+
+    let mut scene = unlit_scene();
+    scene.add_object(Object::new(Mesh::new(vec![mirror, wall])));
+    scene
+}
+
+#[test]
+fn mirrors_show_what_is_in_front_of_them() {
+    let scene = mirror_scene(1.0);
+    let bvh = Bvh::from_scene(&scene);
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    let color = trace(&scene, &bvh, &ray, 0);
+    assert_close(1.0, color.r);
+    assert_close(0.0, color.g);
+}
+
+#[test]
+fn reflections_blend_with_surface_colour() {
+    let scene = mirror_scene(0.25);
+    let bvh = Bvh::from_scene(&scene);
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    let color = trace(&scene, &bvh, &ray, 0);
+    assert_close(1.0, color.r);
+    assert_close(0.75, color.g);
+    assert_close(0.75, color.b);
+}
+
+#[test]
+fn reflections_stop_at_max_depth() {
+    let mut scene = mirror_scene(1.0);
+    let bvh = Bvh::from_scene(&scene);
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    scene.render_settings.max_depth = 0;
+    let color = trace(&scene, &bvh, &ray, 0);
+    assert_close(1.0, color.g);
+
+    // A ray that has already bounced as many times as allowed shouldn't
+    // bounce again either.
+    scene.render_settings.max_depth = 3;
+    let color = trace(&scene, &bvh, &ray, 3);
+    assert_close(1.0, color.g);
+    let color = trace(&scene, &bvh, &ray, 2);
+    assert_close(0.0, color.g);
+}
+
+#[test]
+fn reflections_that_miss_are_black() {
+    let mut scene = mirror_scene(1.0);
+    scene.objects[0].mesh.triangles.pop();
+    let bvh = Bvh::from_scene(&scene);
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    let color = trace(&scene, &bvh, &ray, 0);
+    assert_close(0.0, color.r);
+    assert_close(1.0, color.a);
+}
+
+#[test]
+fn refraction_follows_snells_law() {
+    let normal = Point::new(0.0, 0.0, -1.0);
+
+    // Straight on there is no bending at all
+    let (direction, cos_t) = refract(&Point::new(0.0, 0.0, 1.0), &normal, 1.0 / 1.5).unwrap();
+    assert_eq!(Point::new(0.0, 0.0, 1.0), direction);
+    assert_close(1.0, cos_t);
+
+    // At 45 degrees going into glass, sin(out) = sin(in) / 1.5
+    let incoming = Point::new(1.0, 0.0, 1.0).normalized();
+    let (direction, cos_t) = refract(&incoming, &normal, 1.0 / 1.5).unwrap();
+    let sin_t = std::f64::consts::FRAC_1_SQRT_2 / 1.5;
+    assert_close(sin_t, direction.x);
+    assert_close((1.0 - sin_t * sin_t).sqrt(), direction.z);
+    assert_close(direction.z, cos_t);
+    assert_close(1.0, direction.length());
+}
+
+#[test]
+fn total_internal_reflection() {
+    // Leaving glass at 60 degrees is past the critical angle of about 42
+    let normal = Point::new(0.0, 0.0, -1.0);
+    let angle = std::f64::consts::FRAC_PI_3;
+    let incoming = Point::new(angle.sin(), 0.0, angle.cos());
+
+    assert!(refract(&incoming, &normal, 1.5).is_none());
+    assert!(refract(&incoming, &normal, 1.0 / 1.5).is_some());
+}
+
+#[test]
+fn fresnel_reflects_more_at_grazing_angles() {
+    assert_close(0.04, schlick(1.0, 1.0, 1.0, 1.5));
+    assert!(schlick(0.1, 0.6, 1.0, 1.5) > 0.5);
+    assert_close(1.0, schlick(0.0, 0.5, 1.0, 1.5));
+}
+
+#[test]
+fn see_through_surfaces_show_what_is_behind_them() {
+    let mut glass_material = Material::new(WHITE);
+    glass_material.set_transmission(1.0);
+    glass_material.set_index_of_refraction(1.5);
+    let mut glass = Triangle::new([
+        Point::new(0.0, 1.0, 1.0),
+        Point::new(1.0, -1.0, 1.0),
+        Point::new(-1.0, -1.0, 1.0)
+    ]);
+    glass.set_material(glass_material);
+
+    let mut wall = Triangle::new([
+        Point::new(0.0, 10.0, 5.0),
+        Point::new(10.0, -10.0, 5.0),
+        Point::new(-10.0, -10.0, 5.0)
+    ]);
+    wall.set_material(Material::new(Color::new(255, 0, 0, 255)));
+
+    let mut scene = unlit_scene();
+    scene.add_object(Object::new(Mesh::new(vec![glass, wall])));
+    let bvh = Bvh::from_scene(&scene);
+    let ray = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+
+    // Straight on, 4% is reflected (and sees nothing) and the rest gets
+    // through to the wall.
+    let color = trace(&scene, &bvh, &ray, 0);
+    assert_close(0.96, color.r);
+    assert_close(0.0, color.g);
+
+    // Without any depth left the glass just shows its own colour
+    let color = trace(&scene, &bvh, &ray, scene.render_settings.max_depth);
+    assert_close(1.0, color.g);
+}
+
+#[test]
+fn missed_rays_see_the_background() {
+    let mut scene = Scene::new_empty();
+    scene.background = Background::Gradient {
+        bottom: Color::new(0, 0, 0, 255),
+        top: Color::new(0, 0, 255, 255)
+    };
+    let bvh = Bvh::from_scene(&scene);
+
+    let up = trace(
+        &scene,
+        &bvh,
+        &Ray::new(ORIGIN, Point::new(0.0, 1.0, 0.0)),
+        0
+    );
+    assert_close(1.0, up.b);
+    let level = trace(
+        &scene,
+        &bvh,
+        &Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0)),
+        0
+    );
+    assert_close(0.5, level.b);
+}
+
+#[test]
+fn mirrors_reflect_the_background() {
+    let mut mirror_material = Material::new(WHITE);
+    mirror_material.set_reflectivity(1.0);
+    let mut mirror = Triangle::new([
+        Point::new(0.0, 1.0, 1.0),
+        Point::new(1.0, -1.0, 1.0),
+        Point::new(-1.0, -1.0, 1.0)
+    ]);
+    mirror.set_material(mirror_material);
+
+    let mut scene = unlit_scene();
+    scene.background = Background::Solid(Color::new(0, 0, 255, 255));
+    scene.add_object(Object::new(Mesh::new(vec![mirror])));
+    let bvh = Bvh::from_scene(&scene);
+
+    let color = trace(
+        &scene,
+        &bvh,
+        &Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0)),
+        0
+    );
+    assert_close(0.0, color.r);
+    assert_close(1.0, color.b);
+}
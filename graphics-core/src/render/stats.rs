@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests;
+
+use std::cell::Cell;
+use std::fmt;
+use std::time::Duration;
+
+thread_local! {
+    /// What the current thread has counted since the counts were last taken.
+    /// Keeping the counts per thread means tracing rays on many threads at
+    /// once never has to wait on shared counters.
+    static COUNTS: Cell<Counts> = Cell::new(Counts::default());
+}
+
+/// A stage of rendering that gets timed on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Building the BVHs, including culling what is outside the camera's view.
+    Acceleration,
+    /// Casting rays for the pixels of the image.
+    Tracing,
+    /// Drawing triangles straight onto the image without casting rays.
+    Rasterizing,
+    /// Turning the high dynamic range image into 8-bit colours.
+    ToneMapping
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Acceleration => "acceleration",
+            Phase::Tracing => "tracing",
+            Phase::Rasterizing => "rasterizing",
+            Phase::ToneMapping => "tone mapping"
+        }
+    }
+}
+
+/// How much work went into a render and where the time went, for comparing
+/// scenes and catching things getting slower.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    /// Rays starting at the camera.
+    pub primary_rays: u64,
+    /// Rays checking whether anything is between a surface and a light.
+    pub shadow_rays: u64,
+    /// Rays that bounced off of or passed through a surface.
+    pub secondary_rays: u64,
+    /// How many times a ray was checked against a single triangle.
+    pub triangle_tests: u64,
+    /// How many BVH nodes rays had to check their bounds against.
+    pub bvh_node_visits: u64,
+    /// How long each phase took, in the order they first happened.
+    pub phase_times: Vec<(Phase, Duration)>
+}
+
+impl RenderStats {
+    /// Every ray of every kind.
+    pub fn rays(&self) -> u64 {
+        self.primary_rays + self.shadow_rays + self.secondary_rays
+    }
+
+    /// How long the phase took, which is zero if it never happened.
+    pub fn phase_time(&self, phase: Phase) -> Duration {
+        self.phase_times
+            .iter()
+            .filter(|(p, _)| *p == phase)
+            .map(|(_, time)| *time)
+            .sum()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.phase_times.iter().map(|(_, time)| *time).sum()
+    }
+
+    /// How quickly rays were traced while tracing, or zero if no time was
+    /// spent tracing.
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.phase_time(Phase::Tracing).as_secs_f64();
+        if seconds > 0.0 {
+            self.rays() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    pub(crate) fn add_phase_time(&mut self, phase: Phase, time: Duration) {
+        match self.phase_times.iter_mut().find(|(p, _)| *p == phase) {
+            Some((_, total)) => *total += time,
+            None => self.phase_times.push((phase, time))
+        }
+    }
+
+    pub(crate) fn add_counts(&mut self, counts: &Counts) {
+        self.primary_rays += counts.primary_rays;
+        self.shadow_rays += counts.shadow_rays;
+        self.secondary_rays += counts.secondary_rays;
+        self.triangle_tests += counts.triangle_tests;
+        self.bvh_node_visits += counts.bvh_node_visits;
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "primary rays:    {}", self.primary_rays)?;
+        writeln!(f, "shadow rays:     {}", self.shadow_rays)?;
+        writeln!(f, "secondary rays:  {}", self.secondary_rays)?;
+        writeln!(f, "triangle tests:  {}", self.triangle_tests)?;
+        writeln!(f, "BVH node visits: {}", self.bvh_node_visits)?;
+        for (phase, time) in &self.phase_times {
+            writeln!(f, "{}: {:.3} ms", phase.name(), time.as_secs_f64() * 1000.0)?;
+        }
+        writeln!(
+            f,
+            "total: {:.3} ms",
+            self.total_time().as_secs_f64() * 1000.0
+        )?;
+        write!(f, "rays per second: {:.0}", self.rays_per_second())
+    }
+}
+
+/// The counts that are kept for each thread while rendering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Counts {
+    pub primary_rays: u64,
+    pub shadow_rays: u64,
+    pub secondary_rays: u64,
+    pub triangle_tests: u64,
+    pub bvh_node_visits: u64
+}
+
+/// Adds to the current thread's counts.
+pub(crate) fn count<F: FnOnce(&mut Counts)>(f: F) {
+    COUNTS.with(|cell| {
+        let mut counts = cell.get();
+        f(&mut counts);
+        cell.set(counts);
+    });
+}
+
+/// Returns what the current thread has counted and starts it over from zero.
+pub(crate) fn take_counts() -> Counts {
+    COUNTS.with(|cell| cell.take())
+}
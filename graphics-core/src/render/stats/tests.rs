@@ -0,0 +1,56 @@
+use super::*;
+use std::thread;
+
+#[test]
+fn phase_times_add_up() {
+    let mut stats = RenderStats::default();
+    stats.add_phase_time(Phase::Tracing, Duration::from_millis(300));
+    stats.add_phase_time(Phase::Acceleration, Duration::from_millis(50));
+    stats.add_phase_time(Phase::Tracing, Duration::from_millis(200));
+
+    assert_eq!(Phase::Tracing, stats.phase_times[0].0);
+    assert_eq!(Duration::from_millis(500), stats.phase_time(Phase::Tracing));
+    assert_eq!(Duration::ZERO, stats.phase_time(Phase::ToneMapping));
+    assert_eq!(Duration::from_millis(550), stats.total_time());
+}
+
+#[test]
+fn rays_per_second_only_counts_time_spent_tracing() {
+    let mut stats = RenderStats {
+        primary_rays: 600,
+        shadow_rays: 300,
+        secondary_rays: 100,
+        ..RenderStats::default()
+    };
+    assert_eq!(0.0, stats.rays_per_second());
+
+    stats.add_phase_time(Phase::Tracing, Duration::from_millis(500));
+    stats.add_phase_time(Phase::ToneMapping, Duration::from_millis(500));
+    assert_eq!(1000, stats.rays());
+    assert_eq!(2000.0, stats.rays_per_second());
+
+    let report = stats.to_string();
+    assert!(report.contains("shadow rays:     300"));
+    assert!(report.contains("tone mapping: 500.000 ms"));
+    assert!(report.contains("rays per second: 2000"));
+}
+
+#[test]
+fn counts_are_kept_per_thread() {
+    take_counts();
+    count(|counts| counts.primary_rays += 2);
+
+    let other = thread::spawn(|| {
+        count(|counts| counts.shadow_rays += 5);
+        take_counts()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(5, other.shadow_rays);
+    assert_eq!(0, other.primary_rays);
+
+    let counts = take_counts();
+    assert_eq!(2, counts.primary_rays);
+    assert_eq!(0, counts.shadow_rays);
+    assert_eq!(Counts::default(), take_counts());
+}
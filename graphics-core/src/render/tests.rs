@@ -0,0 +1,459 @@
+use super::*;
+use crate::camera::{Camera, OrthographicCamera, PerspectiveCamera, Renderer};
+use crate::images::tone_mapping::ToneMapOperator;
+use crate::images::{Color, RawImage, WHITE};
+use crate::material::Material;
+use crate::space::object::{Mesh, Object};
+use crate::space::{Point, Triangle};
+use crate::test_support::{quad, unlit_scene};
+
+fn random_triangles(count: usize) -> Object {
+    let mut triangles = Vec::new();
+    for i in 0..count {
+        let offset = (i as f64) * 0.3 - 1.5;
+        let mut triangle = Triangle::new([
+            Point::new(offset, 1.0, 4.0 + offset),
+            Point::new(offset + 1.0, -1.0, 5.0),
+            Point::new(-offset, -0.5, 6.0 - offset)
+        ]);
+        triangle.set_material(Material::new(Color::random()));
+        triangles.push(triangle);
+    }
+
+    Object::new(Mesh::new(triangles))
+}
+
+#[test]
+fn tiles_cover_the_whole_image() {
+    let tiles = split_into_tiles((70, 40), 32);
+
+    assert_eq!(6, tiles.len());
+    assert_eq!(
+        Tile {
+            x: 0,
+            y: 0,
+            width: 32,
+            height: 32
+        },
+        tiles[0]
+    );
+    assert_eq!(
+        Tile {
+            x: 64,
+            y: 32,
+            width: 6,
+            height: 8
+        },
+        tiles[5]
+    );
+
+    let area: usize = tiles.iter().map(|t| t.width * t.height).sum();
+    assert_eq!(70 * 40, area);
+}
+
+#[test]
+fn thread_count_setting() {
+    let mut settings = RenderSettings::new_default();
+    assert!(settings.thread_count() >= 1);
+
+    settings.threads = 3;
+    assert_eq!(3, settings.thread_count());
+}
+
+#[test]
+fn threaded_render_matches_single_threaded() {
+    let mut scene = Scene::new_empty();
+    scene.add_object(random_triangles(10));
+    scene.render_settings.tile_size = 7;
+
+    for camera in [
+        Camera::Ortho(OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 4.0, 3.0)),
+        Camera::Perspective(PerspectiveCamera::new_default())
+    ] {
+        scene.render_settings.threads = 1;
+        let single = camera.render(&scene, (45, 30));
+        scene.render_settings.threads = 4;
+        let threaded = camera.render(&scene, (45, 30));
+
+        assert_eq!(single.get_image_data(), threaded.get_image_data());
+    }
+}
+
+/// Every distinct amount of red in the image.
+fn red_levels(image: &RawImage) -> Vec<u8> {
+    let mut levels = Vec::new();
+    for row in 0..image.get_height() {
+        for col in 0..image.get_width() {
+            let r = image.get_pixel(row, col).color.r;
+            if !levels.contains(&r) {
+                levels.push(r);
+            }
+        }
+    }
+
+    levels
+}
+
+#[test]
+fn supersampling_smooths_edges() {
+    let mut scene = unlit_scene();
+    let mut triangle = Triangle::new([
+        Point::new(-2.0, 2.0, 1.0),
+        Point::new(1.7, -2.0, 1.0),
+        Point::new(-2.0, -2.0, 1.0)
+    ]);
+    triangle.set_material(Material::new(Color::new(255, 0, 0, 255)));
+    scene.add_object(Object::new(Mesh::new(vec![triangle])));
+    let camera = OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0);
+
+    let aliased = camera.render(&scene, (16, 16));
+    assert_eq!(2, red_levels(&aliased).len());
+
+    for filter in [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell
+    ] {
+        scene.render_settings.sampling = Sampling::Jittered(4);
+        scene.render_settings.filter = filter;
+        scene.render_settings.threads = 1;
+        let smooth = camera.render(&scene, (16, 16));
+        assert!(
+            red_levels(&smooth).len() > 2,
+            "{:?} left hard edges",
+            filter
+        );
+
+        // Jittering is still the same no matter how the work is split up
+        scene.render_settings.threads = 4;
+        scene.render_settings.tile_size = 5;
+        let threaded = camera.render(&scene, (16, 16));
+        assert_eq!(smooth.get_image_data(), threaded.get_image_data());
+    }
+}
+
+#[test]
+fn hdr_renders_keep_bright_colours() {
+    let mut scene = Scene::new_empty();
+    // Far more light than white can show
+    scene.render_settings.ambient = 3.0;
+    let mut triangle = Triangle::new([
+        Point::new(0.0, 5.0, 1.0),
+        Point::new(5.0, -5.0, 1.0),
+        Point::new(-5.0, -5.0, 1.0)
+    ]);
+    triangle.set_material(Material::new(Color::new(255, 255, 255, 255)));
+    scene.add_object(Object::new(Mesh::new(vec![triangle])));
+    let camera = Camera::new_default();
+
+    let hdr = camera.render_hdr(&scene, (4, 4));
+    assert!((hdr.get_pixel(2, 2).r - 3.0).abs() < 1e-9);
+
+    let clamped = camera.render(&scene, (4, 4));
+    assert_eq!(255, clamped.get_pixel(2, 2).color.r);
+
+    scene.render_settings.tone_mapping = ToneMapping::new(ToneMapOperator::Reinhard, 0.0, false);
+    let mapped = camera.render(&scene, (4, 4));
+    assert_eq!(191, mapped.get_pixel(2, 2).color.r);
+}
+
+#[test]
+fn render_area_is_clipped_to_the_image() {
+    let mut settings = RenderSettings::new_default();
+    let whole = Tile {
+        x: 0,
+        y: 0,
+        width: 40,
+        height: 30
+    };
+    assert_eq!(whole, settings.render_area((40, 30)));
+
+    settings.region = Some(RenderRegion::new(30, 10, 20, 5, true));
+    assert_eq!(
+        Tile {
+            x: 30,
+            y: 10,
+            width: 10,
+            height: 5
+        },
+        settings.render_area((40, 30))
+    );
+
+    // Entirely off the image, or only touching its edge
+    for region in [
+        RenderRegion::new(100, 100, 10, 10, true),
+        RenderRegion::new(100, 5, 10, 10, true),
+        RenderRegion::new(5, 30, 10, 10, true)
+    ] {
+        settings.region = Some(region);
+        let area = settings.render_area((40, 30));
+        assert_eq!((0, 0), (area.width, area.height));
+    }
+}
+
+#[test]
+fn regions_outside_the_image_render_nothing() {
+    use path_tracer::PathTracer;
+    use rasterizer::{Rasterizer, ShadingMode};
+    use wireframe::{WireframeRenderer, WireframeStyle};
+
+    let mut scene = Scene::new_empty();
+    scene.add_object(random_triangles(5));
+    scene.render_settings.region = Some(RenderRegion::new(100, 100, 10, 10, true));
+    let camera = Camera::new_default();
+    let renderers: Vec<Box<dyn Renderer>> = vec![
+        Box::new(camera.clone()),
+        Box::new(PathTracer::new(camera.clone(), 1, 1)),
+        Box::new(Rasterizer::new(camera.clone(), ShadingMode::Flat)),
+        Box::new(WireframeRenderer::new(
+            camera.clone(),
+            WireframeStyle::new_default(),
+            true
+        )),
+    ];
+
+    for renderer in &renderers {
+        let image = renderer.render(&scene, (40, 30));
+        assert_eq!(0, image.get_width());
+        assert_eq!(0, image.get_height());
+
+        let aovs = renderer.render_aovs(&scene, (40, 30));
+        assert_eq!((0, 0), (aovs.get_width(), aovs.get_height()));
+    }
+
+    // Without cropping it's the whole image with nothing rendered in it
+    scene.render_settings.region = Some(RenderRegion::new(100, 100, 10, 10, false));
+    let image = camera.render(&scene, (40, 30));
+    assert_eq!(40, image.get_width());
+    assert_eq!(Color::new(0, 0, 0, 0), image.get_pixel(15, 20).color);
+}
+
+#[test]
+fn area_tiles_start_at_the_corner_of_the_area() {
+    let area = Tile {
+        x: 5,
+        y: 7,
+        width: 10,
+        height: 4
+    };
+    let tiles = split_area_into_tiles(&area, 4);
+
+    assert_eq!(3, tiles.len());
+    assert_eq!((5, 7), (tiles[0].x, tiles[0].y));
+    assert_eq!(
+        (13, 7, 2, 4),
+        (tiles[2].x, tiles[2].y, tiles[2].width, tiles[2].height)
+    );
+}
+
+#[test]
+fn regions_match_the_same_part_of_a_full_render() {
+    let mut scene = Scene::new_empty();
+    scene.add_object(random_triangles(10));
+    scene.render_settings.tile_size = 4;
+    let camera = Camera::Perspective(PerspectiveCamera::new_default());
+    let full = camera.render(&scene, (30, 20));
+
+    scene.render_settings.region = Some(RenderRegion::new(12, 3, 9, 11, true));
+    let cropped = camera.render(&scene, (30, 20));
+    assert_eq!(9, cropped.get_width());
+    assert_eq!(11, cropped.get_height());
+    for row in 0..11 {
+        for col in 0..9 {
+            assert_eq!(
+                full.get_pixel(row + 3, col + 12).color,
+                cropped.get_pixel(row, col).color
+            );
+        }
+    }
+
+    scene.render_settings.region = Some(RenderRegion::new(12, 3, 9, 11, false));
+    let canvas = camera.render(&scene, (30, 20));
+    assert_eq!(30, canvas.get_width());
+    assert_eq!(full.get_pixel(5, 15).color, canvas.get_pixel(5, 15).color);
+    assert_eq!(Color::new(0, 0, 0, 0), canvas.get_pixel(0, 0).color);
+    assert_eq!(Color::new(0, 0, 0, 0), canvas.get_pixel(19, 29).color);
+}
+
+#[test]
+fn aovs_follow_the_render_region() {
+    let mut scene = Scene::new_empty();
+    scene.add_object(random_triangles(10));
+    let camera = Camera::Perspective(PerspectiveCamera::new_default());
+    scene.render_settings.region = Some(RenderRegion::new(2, 2, 5, 3, true));
+
+    let aovs = camera.render_aovs(&scene, (10, 10));
+    assert_eq!(5, aovs.get_width());
+    assert_eq!(3, aovs.get_height());
+}
+
+#[test]
+fn culled_objects_still_cast_shadows() {
+    use crate::space::lighting::DirectionalLight;
+
+    let mut scene = Scene::new_empty();
+    scene.add_object(quad(
+        [
+            Point::new(-5.0, -1.0, 2.0),
+            Point::new(-5.0, -1.0, 10.0),
+            Point::new(5.0, -1.0, 10.0),
+            Point::new(5.0, -1.0, 2.0)
+        ],
+        Material::new(WHITE)
+    ));
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, -1.0, 1.0),
+        Color::new(255, 255, 255, 255),
+        1.0
+    )));
+    let camera = PerspectiveCamera::new_default();
+    let size = (40, 40);
+    let unshadowed = camera.render_hdr(&scene, size);
+
+    // Above the top of the view, but its shadow falls on the floor in front
+    scene.add_object(quad(
+        [
+            Point::new(-1.0, 3.0, 0.5),
+            Point::new(-1.0, 3.0, 1.5),
+            Point::new(1.0, 3.0, 1.5),
+            Point::new(1.0, 3.0, 0.5)
+        ],
+        Material::new(WHITE)
+    ));
+    // Behind the camera
+    scene.add_object(random_triangles(3));
+    scene.objects[2]
+        .transform
+        .add_step(crate::space::transform::TransformStep::Translate(
+            Point::new(0.0, 0.0, -20.0)
+        ));
+
+    let context = RenderContext::new_default();
+    let culled = camera.render_hdr_with_context(&scene, size, &context);
+    let stats = context.culling_stats().unwrap();
+    assert_eq!(2, stats.objects_culled);
+    assert_eq!(5, stats.triangles_culled);
+    assert_eq!(7, stats.total_triangles);
+
+    let unculled = render_image(
+        &scene,
+        size,
+        &RenderContext::new_default(),
+        None,
+        |x, y, lens| Some(camera.lens_ray(x, y, lens))
+    );
+    assert_eq!(unculled, culled);
+    assert_ne!(unshadowed, culled);
+}
+
+#[test]
+fn flipped_faces_disappear_with_backface_culling() {
+    // Wound so the normal points away from the camera
+    let mut scene = unlit_scene();
+    scene.add_object(quad(
+        [
+            Point::new(-1.0, -1.0, 3.0),
+            Point::new(1.0, -1.0, 3.0),
+            Point::new(1.0, 1.0, 3.0),
+            Point::new(-1.0, 1.0, 3.0)
+        ],
+        Material::new(WHITE)
+    ));
+    let camera = PerspectiveCamera::new_default();
+    let visible = Color::new(255, 255, 255, 255);
+
+    scene.render_settings.backface_culling = true;
+    let image = camera.render(&scene, (9, 9));
+    assert_eq!(visible, image.get_pixel(4, 4).color);
+
+    for triangle in &mut scene.objects[0].mesh.triangles {
+        let mut material = triangle.material().unwrap().clone();
+        material.set_double_sided(false);
+        triangle.set_material(material);
+    }
+    let image = camera.render(&scene, (9, 9));
+    assert_ne!(visible, image.get_pixel(4, 4).color);
+
+    scene.render_settings.backface_culling = false;
+    let image = camera.render(&scene, (9, 9));
+    assert_eq!(visible, image.get_pixel(4, 4).color);
+}
+
+#[test]
+fn stats_count_every_kind_of_ray() {
+    use crate::space::lighting::DirectionalLight;
+    use stats::Phase;
+
+    let mut scene = Scene::new_empty();
+    scene.render_settings.tile_size = 3;
+    // Big enough to fill the whole view
+    scene.add_object(quad(
+        [
+            Point::new(-10.0, -10.0, 3.0),
+            Point::new(-10.0, 10.0, 3.0),
+            Point::new(10.0, 10.0, 3.0),
+            Point::new(10.0, -10.0, 3.0)
+        ],
+        Material::new(WHITE)
+    ));
+    scene.lights.push(Box::new(DirectionalLight::new(
+        Point::new(0.0, 0.0, 1.0),
+        Color::new(255, 255, 255, 255),
+        1.0
+    )));
+    let camera = PerspectiveCamera::new_default();
+
+    let context = RenderContext::new_default();
+    camera.render_with_context(&scene, (8, 6), &context);
+    let stats = context.stats();
+    assert_eq!(48, stats.primary_rays);
+    assert_eq!(48, stats.shadow_rays);
+    assert_eq!(0, stats.secondary_rays);
+    assert!(stats.triangle_tests >= 96);
+    assert!(stats.bvh_node_visits >= 96);
+    for phase in [Phase::Acceleration, Phase::Tracing, Phase::ToneMapping] {
+        assert!(stats.phase_times.iter().any(|(p, _)| *p == phase));
+    }
+
+    // Mirrors send out another ray for every pixel
+    for triangle in &mut scene.objects[0].mesh.triangles {
+        let mut material = triangle.material().unwrap().clone();
+        material.set_reflectivity(0.5);
+        triangle.set_material(material);
+    }
+    let mut counts = Vec::new();
+    for threads in [1, 4] {
+        scene.render_settings.threads = threads;
+        let context = RenderContext::new_default();
+        camera.render_hdr_with_context(&scene, (8, 6), &context);
+        let stats = context.stats();
+        assert_eq!(48, stats.secondary_rays);
+        counts.push((stats.rays(), stats.triangle_tests, stats.bvh_node_visits));
+    }
+    assert_eq!(counts[0], counts[1]);
+}
+
+#[test]
+fn colours_come_back_unchanged_with_srgb_output() {
+    use crate::space::background::Background;
+
+    let grey = Color::new(128, 128, 128, 255);
+    let sky = Color::new(40, 90, 200, 255);
+    let mut scene = unlit_scene();
+    scene.render_settings.tone_mapping.srgb = true;
+    scene.background = Background::Solid(sky.clone());
+    scene.add_object(quad(
+        [
+            Point::new(-1.0, -1.0, 3.0),
+            Point::new(-1.0, 1.0, 3.0),
+            Point::new(1.0, 1.0, 3.0),
+            Point::new(1.0, -1.0, 3.0)
+        ],
+        Material::new(grey.clone())
+    ));
+
+    let image = PerspectiveCamera::new_default().render(&scene, (9, 9));
+    assert_eq!(grey, image.get_pixel(4, 4).color);
+    assert_eq!(sky, image.get_pixel(0, 0).color);
+}
@@ -0,0 +1,268 @@
+#[cfg(test)]
+mod tests;
+
+use super::aov::AovBuffers;
+use super::context::RenderContext;
+use super::rasterizer::{Canvas, View};
+use super::stats::Phase;
+use crate::camera::{Camera, Renderer};
+use crate::images::{Color, FloatColor, FloatImage, WHITE};
+use crate::space::{scene::Scene, Point};
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+/// How much further away than the surface at a pixel a line can be and still
+/// count as being on that surface, relative to the surface's distance. Lines
+/// lie exactly on their triangles, but the depth of a triangle is only known
+/// at pixel centers, so some leeway is needed next to steep triangles.
+const DEPTH_TOLERANCE: f64 = 0.01;
+
+/// How the edges of the triangles are drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireframeStyle {
+    pub color: Color,
+    /// How wide the lines are in pixels.
+    pub thickness: f64,
+    /// Whether edges hidden behind other triangles are left out.
+    pub hidden_line_removal: bool
+}
+
+impl WireframeStyle {
+    pub fn new(color: Color, thickness: f64, hidden_line_removal: bool) -> Self {
+        WireframeStyle {
+            color,
+            thickness,
+            hidden_line_removal
+        }
+    }
+
+    /// White lines one pixel wide, with hidden lines removed.
+    pub fn new_default() -> Self {
+        WireframeStyle::new(WHITE, 1.0, true)
+    }
+}
+
+/// Draws the edges of every triangle in the scene as anti-aliased lines,
+/// which is handy for checking how a mesh is put together. The lines can be
+/// drawn over just the scene's background, or over the image rendered by the
+/// camera. Edges shared by more than one triangle are only drawn once.
+///
+/// Like the [`Rasterizer`](super::rasterizer::Rasterizer), only orthographic
+/// and perspective cameras can draw lines. Other cameras still render the image
+/// underneath, but without any lines on it.
+#[derive(Debug, Clone)]
+pub struct WireframeRenderer {
+    camera: Camera,
+    style: WireframeStyle,
+    /// Whether the lines go over the camera's render instead of the
+    /// background.
+    overlay: bool
+}
+
+impl WireframeRenderer {
+    pub fn new(camera: Camera, style: WireframeStyle, overlay: bool) -> Self {
+        WireframeRenderer {
+            camera,
+            style,
+            overlay
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+
+    pub fn style(&self) -> &WireframeStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: WireframeStyle) {
+        self.style = style;
+    }
+
+    pub fn overlay(&self) -> bool {
+        self.overlay
+    }
+
+    pub fn set_overlay(&mut self, overlay: bool) {
+        self.overlay = overlay;
+    }
+}
+
+impl Renderer for WireframeRenderer {
+    fn render_hdr_with_context(
+        &self,
+        scene: &Scene,
+        image_size: (usize, usize),
+        context: &RenderContext
+    ) -> FloatImage {
+        // Used for the depth of each pixel when hiding lines
+        let mut canvas = Canvas::new(scene, &self.camera, image_size);
+        let mut image = if self.overlay {
+            self.camera
+                .render_hdr_with_context(scene, image_size, context)
+        } else {
+            canvas.image().clone()
+        };
+
+        let view = match View::new(&self.camera) {
+            Some(view) => view,
+            None => return image
+        };
+        let start = Instant::now();
+
+        if self.style.hidden_line_removal {
+            let clear = FloatColor::new(0.0, 0.0, 0.0, 0.0);
+            for obj in &scene.objects {
+                for triangle in obj.triangles() {
+                    if context.is_cancelled() {
+                        context.add_phase_time(Phase::Rasterizing, start.elapsed());
+                        return image;
+                    }
+                    let material = triangle.material().unwrap_or(obj.base_material());
+                    if view.culls(scene, &triangle, material) {
+                        continue;
+                    }
+                    canvas.draw_triangle(&view, &triangle.points, [clear; 3]);
+                }
+            }
+        }
+
+        // How much of each pixel in the full image is covered by a line
+        let mut coverage = vec![0.0; image_size.0 * image_size.1];
+        for (a, b) in unique_edges(scene) {
+            self.draw_line(&view, &canvas, image_size, &a, &b, &mut coverage);
+        }
+
+        let settings = &scene.render_settings;
+        let area = settings.render_area(image_size);
+        let offset = match &settings.region {
+            Some(region) if region.crop => (area.x, area.y),
+            _ => (0, 0)
+        };
+        let line_color = settings.tone_mapping.linear_color(&self.style.color);
+        for j in area.y..(area.y + area.height) {
+            for i in area.x..(area.x + area.width) {
+                let amount = coverage[j * image_size.0 + i];
+                if amount <= 0.0 {
+                    continue;
+                }
+
+                let (row, col) = (j - offset.1, i - offset.0);
+                let blended = *image.get_pixel(row, col) * (1.0 - amount) + line_color * amount;
+                image.set_pixel(blended, row, col);
+            }
+        }
+
+        context.add_phase_time(Phase::Rasterizing, start.elapsed());
+        image
+    }
+
+    /// The AOVs are the same as the camera's, since lines aren't surfaces.
+    fn render_aovs(&self, scene: &Scene, image_size: (usize, usize)) -> AovBuffers {
+        self.camera.render_aovs(scene, image_size)
+    }
+}
+
+impl WireframeRenderer {
+    /// Adds the line between two points in the world to the coverage of each
+    /// pixel it passes over, softening its edges over about a pixel. Parts of
+    /// the line behind what was drawn on the canvas are left out when hidden
+    /// lines are being removed.
+    fn draw_line(
+        &self,
+        view: &View,
+        canvas: &Canvas,
+        image_size: (usize, usize),
+        a: &Point,
+        b: &Point,
+        coverage: &mut [f64]
+    ) {
+        let mut a = view.to_view(a);
+        let mut b = view.to_view(b);
+
+        // Cut off anything too close to the camera
+        let near = view.near();
+        if a.z < near && b.z < near {
+            return;
+        }
+        if a.z < near {
+            a = b + (a - b) * ((near - b.z) / (a.z - b.z));
+        } else if b.z < near {
+            b = a + (b - a) * ((near - a.z) / (b.z - a.z));
+        }
+
+        let (ax, ay, a_weight) = view.project(&a, image_size);
+        let (bx, by, b_weight) = view.project(&b, image_size);
+        let (dx, dy) = (bx - ax, by - ay);
+        let length_squared = dx * dx + dy * dy;
+
+        let half_thickness = self.style.thickness / 2.0;
+        let reach = half_thickness + 1.0;
+        let area = |low: f64, high: f64, size: usize| {
+            let low = (low - reach).floor().max(0.0) as usize;
+            let high = ((high + reach).ceil().max(0.0) as usize).min(size);
+            low..high
+        };
+        let columns = area(ax.min(bx), ax.max(bx), image_size.0);
+        let rows = area(ay.min(by), ay.max(by), image_size.1);
+
+        for j in rows {
+            for i in columns.clone() {
+                let (x, y) = (i as f64 + 0.5, j as f64 + 0.5);
+                // How far along the line the closest point to the pixel is
+                let t = if length_squared > 0.0 {
+                    (((x - ax) * dx + (y - ay) * dy) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = ((ax + dx * t - x).powi(2) + (ay + dy * t - y).powi(2)).sqrt();
+                let amount = (half_thickness + 0.5 - distance).clamp(0.0, 1.0);
+                if amount <= 0.0 {
+                    continue;
+                }
+
+                if self.style.hidden_line_removal {
+                    let weight = a_weight + (b_weight - a_weight) * t;
+                    let depth = (a_weight * a.z + (b_weight * b.z - a_weight * a.z) * t) / weight;
+                    let surface = canvas.depth_at(i, j);
+                    if depth > surface * (1.0 + DEPTH_TOLERANCE) {
+                        continue;
+                    }
+                }
+
+                let index = j * image_size.0 + i;
+                coverage[index] = coverage[index].max(amount);
+            }
+        }
+    }
+}
+
+/// Every edge of every triangle in the scene, with each one only appearing
+/// once even when triangles share it.
+fn unique_edges(scene: &Scene) -> Vec<(Point, Point)> {
+    let key = |point: &Point| [point.x.to_bits(), point.y.to_bits(), point.z.to_bits()];
+
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for obj in &scene.objects {
+        for triangle in obj.triangles() {
+            for k in 0..3 {
+                let a = triangle.points[k];
+                let b = triangle.points[(k + 1) % 3];
+                let (ka, kb) = (key(&a), key(&b));
+                let edge_key = if ka < kb { (ka, kb) } else { (kb, ka) };
+                if seen.insert(edge_key) {
+                    edges.push((a, b));
+                }
+            }
+        }
+    }
+
+    edges
+}
@@ -0,0 +1,108 @@
+use super::*;
+use crate::camera::OrthographicCamera;
+use crate::images::{BLACK, RED};
+use crate::material::Material;
+use crate::space::object::Object;
+use crate::test_support::{count_pixels, quad, unlit_scene};
+
+const GREEN: Color = Color {
+    r: 0,
+    g: 255,
+    b: 0,
+    a: 255
+};
+
+/// A flat square facing the camera made of two triangles, covering `size` in
+/// each direction around the center.
+fn square(size: f64, z: f64, color: Color) -> Object {
+    quad(
+        [
+            Point::new(-size, -size, z),
+            Point::new(-size, size, z),
+            Point::new(size, size, z),
+            Point::new(size, -size, z)
+        ],
+        Material::new(color)
+    )
+}
+
+/// An orthographic camera where each pixel of a 20 by 20 image is a tenth of
+/// a unit across.
+fn camera() -> Camera {
+    Camera::Ortho(OrthographicCamera::new(Point::new(0.0, 0.0, 0.0), 2.0, 2.0))
+}
+
+#[test]
+fn edges_are_drawn_over_the_background() {
+    let mut scene = unlit_scene();
+    scene.add_object(square(0.5, 2.0, RED));
+    let style = WireframeStyle::new(GREEN, 1.0, false);
+
+    let image = WireframeRenderer::new(camera(), style, false).render(&scene, (20, 20));
+
+    // The left edge of the square is at x = -0.5, between pixels 4 and 5, so
+    // it is shared between them
+    let left_edge = &image.get_pixel(10, 5).color;
+    assert!(left_edge.g > 100 && left_edge.g < 255, "{:?}", left_edge);
+    assert_eq!(left_edge, &image.get_pixel(10, 4).color);
+    assert_eq!(BLACK, image.get_pixel(10, 7).color);
+    assert_eq!(BLACK, image.get_pixel(1, 1).color);
+    // The diagonal shared by both triangles goes through the center
+    assert!(image.get_pixel(10, 9).color.g > 100);
+    assert_eq!(0, count_pixels(&image, &RED));
+}
+
+#[test]
+fn edges_can_be_drawn_over_the_render() {
+    let mut scene = unlit_scene();
+    scene.add_object(square(0.5, 2.0, RED));
+    let style = WireframeStyle::new(GREEN, 1.0, false);
+
+    let image = WireframeRenderer::new(camera(), style, true).render(&scene, (20, 20));
+
+    assert_eq!(RED, image.get_pixel(10, 7).color);
+    assert!(image.get_pixel(10, 5).color.g > 100);
+}
+
+#[test]
+fn thicker_lines_cover_more_pixels() {
+    let mut scene = unlit_scene();
+    scene.add_object(square(0.45, 2.0, RED));
+
+    let covered = |thickness: f64| {
+        let style = WireframeStyle::new(GREEN, thickness, false);
+        let image = WireframeRenderer::new(camera(), style, false).render(&scene, (20, 20));
+        20 * 20 - count_pixels(&image, &BLACK)
+    };
+
+    assert!(covered(3.0) > covered(1.0) * 2);
+}
+
+#[test]
+fn hidden_lines_can_be_removed() {
+    let mut scene = unlit_scene();
+    scene.add_object(square(0.7, 2.0, RED));
+    // Behind the red square, so none of its edges can be seen
+    scene.add_object(square(0.3, 3.0, RED));
+
+    let lines_at = |hidden_line_removal: bool| {
+        let style = WireframeStyle::new(GREEN, 1.0, hidden_line_removal);
+        let image = WireframeRenderer::new(camera(), style, true).render(&scene, (20, 20));
+        image.get_pixel(10, 7).color.g
+    };
+
+    assert!(lines_at(false) > 100);
+    assert_eq!(0, lines_at(true));
+}
+
+#[test]
+fn visible_lines_are_kept_when_hiding_lines() {
+    let mut scene = unlit_scene();
+    scene.add_object(square(0.5, 2.0, RED));
+    let style = WireframeStyle::new(GREEN, 1.0, true);
+
+    let image = WireframeRenderer::new(camera(), style, true).render(&scene, (20, 20));
+
+    assert!(image.get_pixel(10, 5).color.g > 100);
+    assert!(image.get_pixel(10, 9).color.g > 100);
+}
@@ -6,12 +6,16 @@
 #[cfg(test)]
 mod tests;
 
+pub mod background;
+pub mod bounds;
+pub mod frustum;
 pub mod lighting;
 pub mod object;
 pub mod scene;
 pub mod transform;
 
 use crate::material::Material;
+use bounds::BoundingBox;
 use transform::Transform;
 
 use std::ops::{Add, Div, Mul, Sub};
@@ -339,6 +343,21 @@ impl Triangle {
         Point::new(x / 3.0, y / 3.0, z / 3.0)
     }
 
+    /// The unit vector perpendicular to the surface of the triangle. Which side
+    /// it points out of follows the right hand rule going around the points
+    /// in order.
+    pub fn normal(&self) -> Vector {
+        let e1 = self.points[1] - self.points[0];
+        let e2 = self.points[2] - self.points[0];
+
+        e1.cross(&e2).normalized()
+    }
+
+    /// The smallest axis aligned box that contains the whole triangle.
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::from_points(&self.points)
+    }
+
     pub fn transformed_triangle(&self, transform: &Transform) -> Self {
         let mut result = self.clone();
 
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests;
+
+use super::Vector;
+use crate::images::tone_mapping::ToneMapping;
+use crate::images::{Color, FloatColor, FloatImage, RawImage};
+
+use std::f64::consts::PI;
+
+/// What rays see when they don't hit anything in the scene.
+#[derive(Debug, Clone)]
+pub enum Background {
+    /// The same colour in every direction.
+    Solid(Color),
+    /// A sky that blends from one colour straight down to another straight up.
+    Gradient { bottom: Color, top: Color },
+    /// A panoramic image wrapped around the whole scene.
+    Map(EnvironmentMap)
+}
+
+impl Background {
+    /// A black background, which is what the scene uses unless told otherwise.
+    pub fn new_default() -> Self {
+        Background::Solid(Color::new(0, 0, 0, 255))
+    }
+
+    /// The colour seen looking in the given direction, which should be a unit
+    /// vector. 8-bit colours, including maps loaded from 8-bit images, are
+    /// turned into linear values the same way as every other colour in the
+    /// scene, following the tone mapping.
+    pub fn color_in_direction(&self, direction: &Vector, tone_mapping: &ToneMapping) -> FloatColor {
+        match self {
+            Background::Solid(color) => tone_mapping.linear_color(color),
+            Background::Gradient { bottom, top } => {
+                let t = ((direction.y + 1.0) / 2.0).clamp(0.0, 1.0);
+                tone_mapping.linear_color(bottom) * (1.0 - t) + tone_mapping.linear_color(top) * t
+            }
+            Background::Map(map) => map.sample(direction, tone_mapping)
+        }
+    }
+}
+
+/// An equirectangular (latitude and longitude) image of everything around the
+/// scene. The left and right edges of the image meet behind the scene at the
+/// negative z direction, the center is the positive z direction, and the top
+/// and bottom rows are straight up and straight down.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    image: FloatImage,
+    /// Whether the pixels are 8-bit colours as they were loaded, rather than
+    /// linear values.
+    eight_bit: bool
+}
+
+impl EnvironmentMap {
+    pub fn new(image: FloatImage) -> Self {
+        assert!(image.get_width() > 0 && image.get_height() > 0);
+
+        EnvironmentMap {
+            image,
+            eight_bit: false
+        }
+    }
+
+    /// Makes a map out of an 8-bit image, like one loaded from a PNG. The
+    /// pixels are kept as they are and only decoded when sampled, following
+    /// the tone mapping like a solid background of the same colour.
+    pub fn from_image(image: &RawImage) -> Self {
+        let mut pixels = Vec::with_capacity(image.get_width() * image.get_height());
+        for row in 0..image.get_height() {
+            for col in 0..image.get_width() {
+                pixels.push(FloatColor::from_color(&image.get_pixel(row, col).color));
+            }
+        }
+
+        let mut map = EnvironmentMap::new(FloatImage::from_pixels(
+            image.get_width(),
+            image.get_height(),
+            pixels
+        ));
+        map.eight_bit = true;
+        map
+    }
+
+    /// Loads the map from a PNG file.
+    pub fn load_png(path: &str) -> Result<Self, String> {
+        Ok(EnvironmentMap::from_image(&RawImage::load_from_path(path)?))
+    }
+
+    pub fn image(&self) -> &FloatImage {
+        &self.image
+    }
+
+    /// The linear colour of the map in the given direction, blended between
+    /// the four closest pixels. The tone mapping decides how the pixels of an
+    /// 8-bit map are decoded, see [`ToneMapping::linear`].
+    pub fn sample(&self, direction: &Vector, tone_mapping: &ToneMapping) -> FloatColor {
+        let direction = direction.normalized();
+        let u = 0.5 + direction.x.atan2(direction.z) / (2.0 * PI);
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+
+        let width = self.image.get_width();
+        let height = self.image.get_height();
+        // Pixel centers are half a pixel in from the edges
+        let x = u * width as f64 - 0.5;
+        let y = (v * height as f64 - 0.5).clamp(0.0, (height - 1) as f64);
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let (tx, ty) = (x - x0, y - y0);
+        // Wrap around horizontally, but not vertically
+        let col = |offset: f64| (x0 + offset).rem_euclid(width as f64) as usize;
+        let row = |offset: f64| ((y0 + offset) as usize).min(height - 1);
+
+        let pixel = |r: usize, c: usize| {
+            let texel = *self.image.get_pixel(r, c);
+            if self.eight_bit {
+                tone_mapping.linear(texel)
+            } else {
+                texel
+            }
+        };
+        let top = pixel(row(0.0), col(0.0)) * (1.0 - tx) + pixel(row(0.0), col(1.0)) * tx;
+        let bottom = pixel(row(1.0), col(0.0)) * (1.0 - tx) + pixel(row(1.0), col(1.0)) * tx;
+
+        top * (1.0 - ty) + bottom * ty
+    }
+}
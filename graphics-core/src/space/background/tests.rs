@@ -0,0 +1,123 @@
+use super::*;
+use crate::images::tone_mapping::ToneMapOperator;
+use crate::space::Point;
+
+fn assert_color(expected: (f64, f64, f64), actual: FloatColor) {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    assert!(
+        close(expected.0, actual.r) && close(expected.1, actual.g) && close(expected.2, actual.b),
+        "expected {:?} but got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// A map four pixels wide and two tall where every pixel has a different
+/// amount of red, and the bottom row is all green.
+fn test_map() -> EnvironmentMap {
+    let mut image = FloatImage::new(4, 2);
+    for col in 0..4 {
+        image.set_pixel(FloatColor::new(col as f64, 0.0, 0.0, 1.0), 0, col);
+        image.set_pixel(FloatColor::new(0.0, 1.0, 0.0, 1.0), 1, col);
+    }
+
+    EnvironmentMap::new(image)
+}
+
+#[test]
+fn solid_background_is_the_same_everywhere() {
+    let background = Background::Solid(Color::new(255, 0, 51, 255));
+
+    for direction in [Point::new(0.0, 1.0, 0.0), Point::new(0.6, 0.0, -0.8)] {
+        assert_color(
+            (1.0, 0.0, 0.2),
+            background.color_in_direction(&direction, &ToneMapping::new_default())
+        );
+    }
+}
+
+#[test]
+fn gradient_blends_from_bottom_to_top() {
+    let background = Background::Gradient {
+        bottom: Color::new(0, 0, 0, 255),
+        top: Color::new(0, 0, 255, 255)
+    };
+
+    let up = background.color_in_direction(&Point::new(0.0, 1.0, 0.0), &ToneMapping::new_default());
+    let down =
+        background.color_in_direction(&Point::new(0.0, -1.0, 0.0), &ToneMapping::new_default());
+    let level =
+        background.color_in_direction(&Point::new(1.0, 0.0, 0.0), &ToneMapping::new_default());
+
+    assert_color((0.0, 0.0, 1.0), up);
+    assert_color((0.0, 0.0, 0.0), down);
+    assert_color((0.0, 0.0, 0.5), level);
+}
+
+#[test]
+fn map_is_sampled_by_direction() {
+    let map = test_map();
+    let tone_mapping = ToneMapping::new_default();
+
+    // Straight up and down use the top and bottom rows
+    assert_eq!(0.0, map.sample(&Point::new(0.0, 1.0, 0.0), &tone_mapping).g);
+    assert_eq!(
+        1.0,
+        map.sample(&Point::new(0.0, -1.0, 0.0), &tone_mapping).g
+    );
+
+    // Looking 45 degrees up is the middle of the top row, and looking forward
+    // lands halfway between the middle columns
+    let forward = map.sample(&Point::new(0.0, 1.0, 1.0), &tone_mapping);
+    assert_color((1.5, 0.0, 0.0), forward);
+
+    // Looking backwards lands where the left and right edges meet
+    let backward = map.sample(&Point::new(0.0, 1.0, -1.0), &tone_mapping);
+    assert_color((1.5, 0.0, 0.0), backward);
+    let right_of_backward = map.sample(&Point::new(-0.2, 1.0, -1.0), &tone_mapping);
+    assert!(right_of_backward.r < 1.5);
+
+    // Maps made from floats are already linear, so are never decoded
+    let srgb = ToneMapping::new(ToneMapOperator::Clamp, 0.0, true);
+    assert_eq!(forward, map.sample(&Point::new(0.0, 1.0, 1.0), &srgb));
+}
+
+#[test]
+fn map_can_be_loaded_from_png() {
+    let mut image = RawImage::new(2, 1);
+    image.set_pixel(Color::new(255, 0, 0, 255), 0, 0);
+    image.set_pixel(Color::new(0, 0, 255, 255), 0, 1);
+    let path = std::env::temp_dir().join(format!("yapre_environment_{}.png", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    image.save_image_to_path(path).unwrap();
+    let map = EnvironmentMap::load_png(path);
+    std::fs::remove_file(path).unwrap();
+
+    let map = map.unwrap();
+    assert_eq!(2, map.image().get_width());
+    assert_eq!(
+        &FloatColor::new(0.0, 0.0, 1.0, 1.0),
+        map.image().get_pixel(0, 1)
+    );
+    assert!(EnvironmentMap::load_png(path).is_err());
+}
+
+#[test]
+fn images_are_decoded_like_solid_colours() {
+    let grey = Color::new(128, 128, 128, 255);
+    let mut image = RawImage::new(1, 1);
+    image.set_pixel(grey.clone(), 0, 0);
+    let map = Background::Map(EnvironmentMap::from_image(&image));
+    let solid = Background::Solid(grey.clone());
+    let direction = Point::new(0.0, 0.0, 1.0);
+
+    // Either way the map and a solid background of its colour look the same,
+    // and both come back out of a render as the colour in the image
+    for srgb in [false, true] {
+        let tone_mapping = ToneMapping::new(ToneMapOperator::Clamp, 0.0, srgb);
+        let seen = map.color_in_direction(&direction, &tone_mapping);
+        assert_eq!(solid.color_in_direction(&direction, &tone_mapping), seen);
+        assert_eq!(grey, tone_mapping.map(&seen));
+    }
+}
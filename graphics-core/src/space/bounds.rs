@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests;
+
+use super::{Point, Triangle, Vector};
+use crate::collision::Ray;
+
+/// A box lined up with the x, y, and z axes that contains some amount of
+/// space, described by its minimum and maximum corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point
+}
+
+impl BoundingBox {
+    pub fn new(min: Point, max: Point) -> Self {
+        BoundingBox { min, max }
+    }
+
+    /// A box that contains nothing at all. Including any point in it will
+    /// create a box around just that point.
+    pub fn new_empty() -> Self {
+        BoundingBox {
+            min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY)
+        }
+    }
+
+    pub fn from_points(points: &[Point]) -> Self {
+        let mut result = BoundingBox::new_empty();
+        for point in points {
+            result.include_point(point);
+        }
+
+        result
+    }
+
+    /// The smallest box around all of the triangles, which should already be
+    /// where they are in the world. See [`Object::bounds`](super::object::Object::bounds).
+    pub fn around_triangles<'a, I: IntoIterator<Item = &'a Triangle>>(triangles: I) -> Self {
+        let mut result = BoundingBox::new_empty();
+        for triangle in triangles {
+            result = result.union(&triangle.bounds());
+        }
+
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// Grows the box just enough to contain the point.
+    pub fn include_point(&mut self, point: &Point) {
+        self.min = Point::new(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z)
+        );
+        self.max = Point::new(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z)
+        );
+    }
+
+    /// The smallest box that contains both this box and the other one.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let mut result = *self;
+        result.include_point(&other.min);
+        result.include_point(&other.max);
+
+        result
+    }
+
+    pub fn size(&self) -> Vector {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Point {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        let size = self.size();
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Point; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Point::new(a.x, a.y, a.z),
+            Point::new(b.x, a.y, a.z),
+            Point::new(a.x, b.y, a.z),
+            Point::new(b.x, b.y, a.z),
+            Point::new(a.x, a.y, b.z),
+            Point::new(b.x, a.y, b.z),
+            Point::new(a.x, b.y, b.z),
+            Point::new(b.x, b.y, b.z)
+        ]
+    }
+
+    /// Finds the distance along the ray at which it enters the box, using the
+    /// slab method. If the ray starts inside the box this will be zero. Returns
+    /// `None` when the ray misses the box or the box is entirely behind the ray.
+    pub fn ray_entry_distance(&self, ray: &Ray) -> Option<f64> {
+        let origin = ray.location().as_arr();
+        let direction = ray.direction().as_arr();
+        let min = self.min.as_arr();
+        let max = self.max.as_arr();
+
+        let mut near = 0.0f64;
+        let mut far = f64::INFINITY;
+        for axis in 0..3 {
+            let inverse = 1.0 / direction[axis];
+            let mut t0 = (min[axis] - origin[axis]) * inverse;
+            let mut t1 = (max[axis] - origin[axis]) * inverse;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // NaN shows up when the ray is parallel to a side of the box and
+            // starts exactly on it, in which case that axis doesn't limit
+            // anything. f64::max and f64::min ignore NaN which handles that.
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+
+        Some(near)
+    }
+}
@@ -0,0 +1,71 @@
+use super::*;
+use crate::space::ORIGIN;
+
+#[test]
+fn bounding_box_from_points() {
+    let bounds = BoundingBox::from_points(&[
+        Point::new(1.0, -2.0, 3.0),
+        Point::new(-1.0, 2.0, 0.0),
+        Point::new(0.0, 0.0, 5.0)
+    ]);
+
+    assert_eq!(Point::new(-1.0, -2.0, 0.0), bounds.min);
+    assert_eq!(Point::new(1.0, 2.0, 5.0), bounds.max);
+    assert_eq!(Point::new(0.0, 0.0, 2.5), bounds.center());
+    assert_eq!(
+        2.0 * (2.0 * 4.0 + 4.0 * 5.0 + 5.0 * 2.0),
+        bounds.surface_area()
+    );
+}
+
+#[test]
+fn empty_bounding_box() {
+    let empty = BoundingBox::new_empty();
+    let unit = BoundingBox::new(ORIGIN, Point::new(1.0, 1.0, 1.0));
+
+    assert!(empty.is_empty());
+    assert_eq!(0.0, empty.surface_area());
+    assert_eq!(unit, empty.union(&unit));
+}
+
+#[test]
+fn ray_entry_distance() {
+    let bounds = BoundingBox::new(Point::new(-1.0, -1.0, 4.0), Point::new(1.0, 1.0, 6.0));
+
+    let towards = Ray::new(ORIGIN, Point::new(0.0, 0.0, 1.0));
+    assert_eq!(Some(4.0), bounds.ray_entry_distance(&towards));
+
+    let away = Ray::new(ORIGIN, Point::new(0.0, 0.0, -1.0));
+    assert_eq!(None, bounds.ray_entry_distance(&away));
+
+    let past = Ray::new(Point::new(2.0, 0.0, 0.0), Point::new(0.0, 0.0, 1.0));
+    assert_eq!(None, bounds.ray_entry_distance(&past));
+
+    let inside = Ray::new(Point::new(0.0, 0.0, 5.0), Point::new(1.0, 1.0, 0.0));
+    assert_eq!(Some(0.0), bounds.ray_entry_distance(&inside));
+}
+
+#[test]
+fn object_bounds_include_the_transform() {
+    use crate::space::object::{Mesh, Object};
+    use crate::space::transform::TransformStep;
+    use crate::space::Triangle;
+
+    let mut obj = Object::new(Mesh::new(vec![Triangle::new([
+        Point::new(0.0, 1.0, 0.0),
+        Point::new(1.0, -1.0, 0.0),
+        Point::new(-1.0, -1.0, 0.0)
+    ])]));
+    obj.transform
+        .add_step(TransformStep::Translate(Point::new(0.0, 0.0, 5.0)));
+
+    assert_eq!(
+        BoundingBox::new(Point::new(-1.0, -1.0, 5.0), Point::new(1.0, 1.0, 5.0)),
+        obj.bounds()
+    );
+    assert_eq!(
+        obj.bounds(),
+        BoundingBox::around_triangles(&obj.triangles())
+    );
+    assert!(BoundingBox::around_triangles(&[]).is_empty());
+}
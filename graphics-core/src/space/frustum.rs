@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests;
+
+use super::bounds::BoundingBox;
+use super::{Point, Triangle, Vector};
+
+/// A flat plane that splits space in two. The side the normal points towards
+/// counts as the inside.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    /// A unit vector at right angles to the plane.
+    pub normal: Vector,
+    /// How far the plane is from the origin along the normal.
+    pub offset: f64
+}
+
+impl Plane {
+    /// The plane through the point at right angles to the normal, which
+    /// doesn't need to be a unit vector.
+    pub fn new(point: Point, normal: Vector) -> Self {
+        let normal = normal.normalized();
+        Plane {
+            normal,
+            offset: normal.dot(&point)
+        }
+    }
+
+    /// How far the point is from the plane. This is negative for points on the
+    /// outside.
+    pub fn signed_distance(&self, point: &Point) -> f64 {
+        self.normal.dot(point) - self.offset
+    }
+}
+
+/// The space a camera can see, made up of everything on the inside of all of
+/// its planes. Checks against it are conservative, so something that can't
+/// be seen might still pass, but nothing that can be seen is ever rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+    planes: Vec<Plane>
+}
+
+impl Frustum {
+    pub fn new(planes: Vec<Plane>) -> Self {
+        Frustum { planes }
+    }
+
+    pub fn planes(&self) -> &[Plane] {
+        &self.planes
+    }
+
+    /// Checks whether any part of the box could be inside. This is only false
+    /// when the whole box is on the outside of one of the planes.
+    pub fn intersects_box(&self, bounds: &BoundingBox) -> bool {
+        if bounds.is_empty() {
+            return false;
+        }
+
+        self.planes.iter().all(|plane| {
+            // The corner of the box furthest along the normal
+            let corner = Point::new(
+                if plane.normal.x >= 0.0 {
+                    bounds.max.x
+                } else {
+                    bounds.min.x
+                },
+                if plane.normal.y >= 0.0 {
+                    bounds.max.y
+                } else {
+                    bounds.min.y
+                },
+                if plane.normal.z >= 0.0 {
+                    bounds.max.z
+                } else {
+                    bounds.min.z
+                }
+            );
+            plane.signed_distance(&corner) >= 0.0
+        })
+    }
+
+    /// Checks whether any part of the triangle could be inside. This is only
+    /// false when all of its corners are on the outside of one of the planes.
+    pub fn intersects_triangle(&self, triangle: &Triangle) -> bool {
+        self.planes.iter().all(|plane| {
+            triangle
+                .points
+                .iter()
+                .any(|point| plane.signed_distance(point) >= 0.0)
+        })
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+/// The cube from -1 to 1 on every axis.
+fn unit_cube() -> Frustum {
+    let mut planes = Vec::new();
+    for axis in [
+        Point::new(1.0, 0.0, 0.0),
+        Point::new(0.0, 1.0, 0.0),
+        Point::new(0.0, 0.0, 1.0)
+    ] {
+        planes.push(Plane::new(axis * -1.0, axis));
+        planes.push(Plane::new(axis, axis * -1.0));
+    }
+
+    Frustum::new(planes)
+}
+
+#[test]
+fn planes_measure_distance_along_the_normal() {
+    let plane = Plane::new(Point::new(0.0, 2.0, 0.0), Point::new(0.0, 3.0, 0.0));
+
+    assert_eq!(Point::new(0.0, 1.0, 0.0), plane.normal);
+    assert_eq!(1.0, plane.signed_distance(&Point::new(5.0, 3.0, -1.0)));
+    assert_eq!(-2.0, plane.signed_distance(&Point::new(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn boxes_are_only_rejected_when_fully_outside() {
+    let frustum = unit_cube();
+
+    let inside = BoundingBox::new(Point::new(-0.5, -0.5, -0.5), Point::new(0.5, 0.5, 0.5));
+    let overlapping = BoundingBox::new(Point::new(0.5, 0.5, 0.5), Point::new(3.0, 3.0, 3.0));
+    let outside = BoundingBox::new(Point::new(1.5, -0.5, -0.5), Point::new(3.0, 0.5, 0.5));
+
+    assert!(frustum.intersects_box(&inside));
+    assert!(frustum.intersects_box(&overlapping));
+    assert!(!frustum.intersects_box(&outside));
+    assert!(!frustum.intersects_box(&BoundingBox::new_empty()));
+}
+
+#[test]
+fn triangles_are_only_rejected_when_fully_outside() {
+    let frustum = unit_cube();
+
+    let crossing = Triangle::new([
+        Point::new(0.0, 0.0, 0.0),
+        Point::new(5.0, 0.0, 0.0),
+        Point::new(5.0, 5.0, 0.0)
+    ]);
+    let outside = Triangle::new([
+        Point::new(0.0, 2.0, 0.0),
+        Point::new(5.0, 2.0, 0.0),
+        Point::new(5.0, 5.0, 0.0)
+    ]);
+
+    assert!(frustum.intersects_triangle(&crossing));
+    assert!(!frustum.intersects_triangle(&outside));
+}
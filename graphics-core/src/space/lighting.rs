@@ -1,14 +1,23 @@
-use super::{Point, Vector};
+#[cfg(test)]
+mod tests;
+
+use super::{Point, Vector, ORIGIN};
 use crate::images::Color;
 
 // TODO: Most of this trait was just what CoPilot suggested, and not sure exactly
 //       how lighting will be implemented.
-pub trait Light {
+pub trait Light: Send + Sync {
     fn get_color(&self) -> Color;
     fn get_intensity(&self) -> f64;
     fn get_position(&self) -> Point;
     fn get_direction(&self) -> Vector; // TODO: Not sure if this is needed
     fn get_type(&self) -> LightType;
+
+    /// Works out how much of this light reaches the given point, ignoring
+    /// anything that might be in the way. Returns `None` if none of the light
+    /// can reach the point at all, such as when it is outside a spot light's
+    /// cone.
+    fn illumination_at(&self, point: &Point) -> Option<Illumination>;
 }
 
 pub enum LightType {
@@ -16,3 +25,234 @@ pub enum LightType {
     Directional,
     Spot
 }
+
+/// The light arriving at a point from a single light.
+#[derive(Debug, Clone)]
+pub struct Illumination {
+    /// A unit vector pointing from the point back towards the light.
+    pub direction: Vector,
+    /// How far away the light is. This is infinite for directional lights.
+    pub distance: f64,
+    pub color: Color,
+    /// The intensity of the light once it reaches the point, after things like
+    /// distance have been taken into account.
+    pub strength: f64
+}
+
+/// A light that shines equally in every direction from a single point, getting
+/// dimmer with the square of the distance.
+pub struct PointLight {
+    position: Point,
+    color: Color,
+    intensity: f64
+}
+
+impl PointLight {
+    pub fn new(position: Point, color: Color, intensity: f64) -> Self {
+        PointLight {
+            position,
+            color,
+            intensity
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn get_color(&self) -> Color {
+        self.color.clone()
+    }
+
+    fn get_intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    fn get_position(&self) -> Point {
+        self.position
+    }
+
+    /// Point lights shine in every direction, so this is always zero.
+    fn get_direction(&self) -> Vector {
+        ORIGIN
+    }
+
+    fn get_type(&self) -> LightType {
+        LightType::Point
+    }
+
+    fn illumination_at(&self, point: &Point) -> Option<Illumination> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+        if distance == 0.0 {
+            return None;
+        }
+
+        Some(Illumination {
+            direction: to_light / distance,
+            distance,
+            color: self.color.clone(),
+            strength: self.intensity / (distance * distance)
+        })
+    }
+}
+
+/// A light that is infinitely far away so all of its rays are parallel and it
+/// is equally bright everywhere, like the sun.
+pub struct DirectionalLight {
+    /// The direction the light is travelling in.
+    direction: Vector,
+    color: Color,
+    intensity: f64
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector, color: Color, intensity: f64) -> Self {
+        assert_ne!(direction, ORIGIN);
+
+        DirectionalLight {
+            direction: direction.normalized(),
+            color,
+            intensity
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn get_color(&self) -> Color {
+        self.color.clone()
+    }
+
+    fn get_intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// Directional lights are infinitely far away and don't really have a
+    /// position, so this is always the origin.
+    fn get_position(&self) -> Point {
+        ORIGIN
+    }
+
+    fn get_direction(&self) -> Vector {
+        self.direction
+    }
+
+    fn get_type(&self) -> LightType {
+        LightType::Directional
+    }
+
+    fn illumination_at(&self, _point: &Point) -> Option<Illumination> {
+        Some(Illumination {
+            direction: self.direction * -1.0,
+            distance: f64::INFINITY,
+            color: self.color.clone(),
+            strength: self.intensity
+        })
+    }
+}
+
+/// A point light that only shines in a cone around the direction it is facing.
+pub struct SpotLight {
+    position: Point,
+    direction: Vector,
+    color: Color,
+    intensity: f64,
+    /// The angle in radians between the center of the cone and its edge.
+    cone_angle: f64,
+    /// How much of the cone, from 0 to 1, is used to fade out towards the
+    /// edge. Zero gives a hard edge and one fades all the way from the center.
+    falloff: f64
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        color: Color,
+        intensity: f64,
+        cone_angle: f64,
+        falloff: f64
+    ) -> Self {
+        assert_ne!(direction, ORIGIN);
+
+        SpotLight {
+            position,
+            direction: direction.normalized(),
+            color,
+            intensity,
+            cone_angle,
+            falloff: falloff.clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn cone_angle(&self) -> f64 {
+        self.cone_angle
+    }
+
+    pub fn falloff(&self) -> f64 {
+        self.falloff
+    }
+
+    /// How much of the light gets through at the given angle from the center of
+    /// the cone, from 0 to 1.
+    fn cone_factor(&self, angle: f64) -> f64 {
+        if angle > self.cone_angle {
+            return 0.0;
+        }
+
+        let inner_angle = self.cone_angle * (1.0 - self.falloff);
+        if angle <= inner_angle {
+            return 1.0;
+        }
+
+        // Smoothly fade out between the inner angle and the edge
+        let t = (self.cone_angle - angle) / (self.cone_angle - inner_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn get_color(&self) -> Color {
+        self.color.clone()
+    }
+
+    fn get_intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    fn get_position(&self) -> Point {
+        self.position
+    }
+
+    fn get_direction(&self) -> Vector {
+        self.direction
+    }
+
+    fn get_type(&self) -> LightType {
+        LightType::Spot
+    }
+
+    fn illumination_at(&self, point: &Point) -> Option<Illumination> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+        if distance == 0.0 {
+            return None;
+        }
+        let direction = to_light / distance;
+
+        // The light leaves the spot going the opposite way to `direction`
+        let angle = (direction * -1.0)
+            .dot(&self.direction)
+            .clamp(-1.0, 1.0)
+            .acos();
+        let cone_factor = self.cone_factor(angle);
+        if cone_factor <= 0.0 {
+            return None;
+        }
+
+        Some(Illumination {
+            direction,
+            distance,
+            color: self.color.clone(),
+            strength: self.intensity * cone_factor / (distance * distance)
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use super::*;
+use crate::images::WHITE;
+
+#[test]
+fn point_light_falls_off_with_distance() {
+    let light = PointLight::new(Point::new(0.0, 2.0, 0.0), WHITE, 8.0);
+
+    let near = light.illumination_at(&Point::new(0.0, 0.0, 0.0)).unwrap();
+    assert_eq!(Point::new(0.0, 1.0, 0.0), near.direction);
+    assert_eq!(2.0, near.distance);
+    assert_eq!(2.0, near.strength);
+
+    let far = light.illumination_at(&Point::new(0.0, -2.0, 0.0)).unwrap();
+    assert_eq!(4.0, far.distance);
+    assert_eq!(0.5, far.strength);
+}
+
+#[test]
+fn directional_light_is_the_same_everywhere() {
+    let light = DirectionalLight::new(Point::new(0.0, -3.0, 0.0), WHITE, 1.5);
+
+    for point in [ORIGIN, Point::new(100.0, -40.0, 7.0)] {
+        let illumination = light.illumination_at(&point).unwrap();
+        assert_eq!(Point::new(0.0, 1.0, 0.0), illumination.direction);
+        assert_eq!(f64::INFINITY, illumination.distance);
+        assert_eq!(1.5, illumination.strength);
+        assert_eq!(WHITE, illumination.color);
+    }
+}
+
+#[test]
+fn spot_light_only_lights_inside_its_cone() {
+    let light = SpotLight::new(
+        Point::new(0.0, 1.0, 0.0),
+        Point::new(0.0, -1.0, 0.0),
+        WHITE,
+        1.0,
+        std::f64::consts::FRAC_PI_4,
+        0.0
+    );
+
+    let center = light.illumination_at(&ORIGIN).unwrap();
+    assert_eq!(1.0, center.strength);
+
+    // Just inside and just outside the 45 degree cone
+    assert!(light.illumination_at(&Point::new(0.9, 0.0, 0.0)).is_some());
+    assert!(light.illumination_at(&Point::new(1.1, 0.0, 0.0)).is_none());
+    // Behind the light
+    assert!(light.illumination_at(&Point::new(0.0, 2.0, 0.0)).is_none());
+}
+
+#[test]
+fn spot_light_fades_towards_the_edge() {
+    let light = SpotLight::new(
+        ORIGIN,
+        Point::new(0.0, 0.0, 1.0),
+        WHITE,
+        1.0,
+        std::f64::consts::FRAC_PI_4,
+        0.5
+    );
+
+    let strength_at_angle = |angle: f64| {
+        let point = Point::new(angle.sin(), 0.0, angle.cos());
+        light.illumination_at(&point).map_or(0.0, |i| i.strength)
+    };
+
+    let quarter = std::f64::consts::FRAC_PI_4;
+    // Full strength inside the inner half of the cone
+    assert!((strength_at_angle(quarter * 0.4) - 1.0).abs() < 1e-9);
+    // Half way through the fade
+    assert!((strength_at_angle(quarter * 0.75) - 0.5).abs() < 1e-9);
+    // Getting dimmer towards the edge
+    assert!(strength_at_angle(quarter * 0.9) < strength_at_angle(quarter * 0.6));
+    assert_eq!(0.0, strength_at_angle(quarter * 1.01));
+}
@@ -1,4 +1,4 @@
-use super::{transform::Transform, Triangle};
+use super::{bounds::BoundingBox, transform::Transform, Triangle};
 use crate::material::Material;
 
 pub struct Mesh {
@@ -49,6 +49,12 @@ impl Object {
         self.mesh.transformed_mesh(&self.transform).triangles
     }
 
+    /// The box around the object in the world, after its transform has been
+    /// applied.
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::around_triangles(&self.triangles())
+    }
+
     pub fn base_material(&self) -> &Material {
         &self.mesh.base_material
     }
@@ -3,14 +3,18 @@ mod tests;
 
 use crate::{
     camera::Camera,
-    space::{lighting::Light, object::Object}
+    render::RenderSettings,
+    space::{background::Background, lighting::Light, object::Object}
 };
 
 pub struct Scene {
     pub primary_camera: Camera,
     pub cameras: Vec<Camera>,
     pub objects: Vec<Object>,
-    pub lights: Vec<Box<dyn Light>>
+    pub lights: Vec<Box<dyn Light>>,
+    /// What is seen where rays don't hit any objects.
+    pub background: Background,
+    pub render_settings: RenderSettings
 }
 
 impl Scene {
@@ -27,7 +31,9 @@ impl Scene {
             primary_camera: cameras.remove(0),
             cameras,
             objects,
-            lights
+            lights,
+            background: Background::new_default(),
+            render_settings: RenderSettings::new_default()
         }
     }
 
@@ -1,11 +1,13 @@
 use super::*;
 use crate::camera::OrthographicCamera;
 use crate::camera::Renderer;
-use crate::images::Color;
+use crate::images::{Color, WHITE};
 use crate::material::Material;
+use crate::space::lighting::PointLight;
 use crate::space::object::{Mesh, Object};
 use crate::space::transform::TransformStep;
 use crate::space::{Point, Triangle, ORIGIN};
+use crate::test_support::quad_triangles;
 use std::path::Path;
 
 fn test_diamond() -> Object {
@@ -19,34 +21,85 @@ fn test_diamond() -> Object {
         Point::new(0.0, 0.0, -1.0),
     ];
     let trip = vec![
-        (0, 2, 3),
-        (0, 3, 4),
-        (0, 4, 5),
+        (0, 2, 4),
+        (0, 4, 3),
+        (0, 3, 5),
         (0, 5, 2),
-        (1, 2, 3),
-        (1, 3, 4),
-        (1, 4, 5),
+        (1, 2, 4),
+        (1, 4, 3),
+        (1, 3, 5),
         (1, 5, 2),
     ];
 
+    let mut material = Material::new(Color::new(0xEE, 0xF4, 0xFF, 0xFF));
+    material.set_specular(0.8);
+    material.set_shininess(200.0);
+    material.set_reflectivity(0.1);
+    material.set_transmission(0.9);
+    material.set_index_of_refraction(2.42);
+
     for t in trip {
         let mut triangle = Triangle::new([points[t.0], points[t.1], points[t.2]]);
-        triangle.set_material(Material::new(Color::random()));
+        // Make sure every face points outwards so light can tell when it is
+        // going in or out of the diamond.
+        if triangle.normal().dot(&triangle.geometric_center()) < 0.0 {
+            triangle.points.swap(1, 2);
+        }
+        triangle.set_material(material.clone());
         triangles.push(triangle);
     }
 
     Object::new(Mesh::new(triangles))
 }
 
+/// A checkerboard wall for the diamond to sit in front of, so that there is
+/// something to see through it.
+fn test_backdrop(z: f64) -> Object {
+    let mut triangles = Vec::new();
+    for row in -4..4 {
+        for col in -4..4 {
+            let color = if (row + col) % 2 == 0 {
+                Color::new(0x30, 0x50, 0x90, 0xFF)
+            } else {
+                Color::new(0xE0, 0xE0, 0xD0, 0xFF)
+            };
+            let (x, y) = (col as f64 * 0.5, row as f64 * 0.5);
+            let corners = [
+                Point::new(x, y, z),
+                Point::new(x + 0.5, y, z),
+                Point::new(x + 0.5, y + 0.5, z),
+                Point::new(x, y + 0.5, z)
+            ];
+            triangles.extend(quad_triangles(corners, &Material::new(color)));
+        }
+    }
+
+    Object::new(Mesh::new(triangles))
+}
+
 #[test]
 fn test_scene_diamond_render() {
     let cam = OrthographicCamera::new(ORIGIN, 3.0, 3.0);
     let mut scene = Scene::new(vec![Camera::Ortho(cam)], Vec::new(), Vec::new());
     let mut obj = test_diamond();
+    obj.transform
+        .add_step(TransformStep::Rotate(Point::new(0.3, 0.6, 0.0), ORIGIN));
     obj.transform
         .add_step(TransformStep::Translate(Point::new(0.0, 0.0, 5.0)));
     obj.squash_transforms();
     scene.add_object(obj);
+    scene.add_object(test_backdrop(8.0));
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(-3.0, 4.0, 0.0),
+        WHITE,
+        40.0
+    )));
+    scene.lights.push(Box::new(PointLight::new(
+        Point::new(2.0, 1.0, -1.0),
+        WHITE,
+        30.0
+    )));
+    scene.render_settings.max_depth = 8;
 
     let img = scene.get_primary_camera().render(&scene, (100, 100));
     match img.save_to_temp_path() {
@@ -59,3 +112,13 @@ fn test_scene_diamond_render() {
         Err(e) => panic!("{}", e)
     };
 }
+
+#[test]
+fn test_new_scene_uses_perspective_camera() {
+    let scene = Scene::new_empty();
+
+    match scene.get_primary_camera() {
+        Camera::Perspective(_) => {}
+        _ => panic!("scenes should default to a perspective camera")
+    }
+}
@@ -179,3 +179,12 @@ fn test_triangle_equality_not_equal() {
 fn triangle_with_duplicate_points_should_panic() {
     Triangle::new([ORIGIN, ORIGIN, Point::new(1.0, 0.0, 0.0)]);
 }
+
+#[test]
+fn triangle_normal_follows_winding_order() {
+    let a = Triangle::new([ORIGIN, Point::new(1.0, 0.0, 0.0), Point::new(0.0, 1.0, 0.0)]);
+    let b = Triangle::new([ORIGIN, Point::new(0.0, 1.0, 0.0), Point::new(1.0, 0.0, 0.0)]);
+
+    assert_eq!(Point::new(0.0, 0.0, 1.0), a.normal());
+    assert_eq!(Point::new(0.0, 0.0, -1.0), b.normal());
+}
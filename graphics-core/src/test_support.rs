@@ -0,0 +1,47 @@
+//! Helpers shared by the tests of more than one module.
+
+use crate::images::{Color, RawImage};
+use crate::material::Material;
+use crate::space::object::{Mesh, Object};
+use crate::space::scene::Scene;
+use crate::space::{Point, Triangle};
+
+/// An empty scene where every surface shows its colour exactly, for tests that
+/// only care about what can be seen.
+pub fn unlit_scene() -> Scene {
+    let mut scene = Scene::new_empty();
+    scene.render_settings.ambient = 1.0;
+    scene
+}
+
+/// The two triangles of a flat quad, split from the first corner to the third.
+/// Both keep the winding of the corners.
+pub fn quad_triangles(corners: [Point; 4], material: &Material) -> Vec<Triangle> {
+    let mut triangles = vec![
+        Triangle::new([corners[0], corners[1], corners[2]]),
+        Triangle::new([corners[0], corners[2], corners[3]]),
+    ];
+    for triangle in &mut triangles {
+        triangle.set_material(material.clone());
+    }
+
+    triangles
+}
+
+/// An object that is a single quad, see [`quad_triangles`].
+pub fn quad(corners: [Point; 4], material: Material) -> Object {
+    Object::new(Mesh::new(quad_triangles(corners, &material)))
+}
+
+pub fn count_pixels(image: &RawImage, color: &Color) -> usize {
+    let mut count = 0;
+    for row in 0..image.get_height() {
+        for col in 0..image.get_width() {
+            if &image.get_pixel(row, col).color == color {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
@@ -13,8 +13,16 @@ use sdl2::rect::{Point, Rect};
 use std::time::Duration;
 
 use yapre_graphics_core::{
-    camera::{OrthographicCamera, Renderer},
+    camera::{Camera, OrthographicCamera, Renderer},
+    images::WHITE,
+    render::{
+        context::RenderContext,
+        progressive::AccumulationBuffer,
+        rasterizer::{Rasterizer, ShadingMode},
+        stats::RenderStats
+    },
     space::{
+        lighting::DirectionalLight,
         object::{Mesh, Object},
         scene::Scene,
         Point as YaprePoint, Triangle, ORIGIN
@@ -79,12 +87,21 @@ fn main() -> Result<(), String> {
     ]);
     // TODO: create the object and scene
     let cam = OrthographicCamera::new(ORIGIN, 3.0, 3.0);
+    // Rasterizing is quick enough to keep up with the animation
+    let preview = Rasterizer::new(Camera::Ortho(cam.clone()), ShadingMode::Flat);
     let mut animation_frame: i32 = 0;
     let animation_frame_max: i32 = 25;
     let animation_frame_min: i32 = -25;
     let mut animation_direction: i32 = 1;
     let mut animation_rotation: f64 = 0.0;
     let animation_rotation_max: f64 = std::f64::consts::PI * 2.0;
+    // Pausing the animation switches to ray tracing, and lets the samples add
+    // up so the image gets smoother
+    let mut paused = false;
+    let rend_size = 300;
+    let mut accumulation = AccumulationBuffer::new(rend_size, rend_size);
+    // What went into the latest preview frame, printed with the S key
+    let mut last_stats = RenderStats::default();
 
     'main_loop: loop {
         for event in event_pump.poll_iter() {
@@ -94,6 +111,18 @@ fn main() -> Result<(), String> {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => break 'main_loop,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    ..
+                } => {
+                    paused = !paused;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    ..
+                } => {
+                    println!("{}", last_stats);
+                }
                 Event::MouseMotion { x, y, .. } => {
                     mouse_x = x;
                     mouse_y = y;
@@ -131,32 +160,48 @@ fn main() -> Result<(), String> {
             canvas.fill_rect(button.rect.clone());
         }*/
 
-        triangle.shift(YaprePoint::new(
-            0.06 * (animation_direction as f64),
-            0.0,
-            0.0
-        ));
-        animation_frame += animation_direction;
-        // I'm not a huge fan of the brace on the next line, but it's alright.
-        // also this file is a hot temporary mess anyway.
-        if (animation_frame >= animation_frame_max && animation_direction > 0)
-            || (animation_frame <= animation_frame_min && animation_direction < 0)
-        {
-            animation_direction = -animation_direction;
-        }
+        if !paused {
+            triangle.shift(YaprePoint::new(
+                0.06 * (animation_direction as f64),
+                0.0,
+                0.0
+            ));
+            animation_frame += animation_direction;
+            // I'm not a huge fan of the brace on the next line, but it's alright.
+            // also this file is a hot temporary mess anyway.
+            if (animation_frame >= animation_frame_max && animation_direction > 0)
+                || (animation_frame <= animation_frame_min && animation_direction < 0)
+            {
+                animation_direction = -animation_direction;
+            }
 
-        animation_rotation += 0.1;
-        if animation_rotation > animation_rotation_max {
-            animation_rotation = 0.0;
-        }
+            animation_rotation += 0.1;
+            if animation_rotation > animation_rotation_max {
+                animation_rotation = 0.0;
+            }
 
-        let rend_size = 300;
+            // The old samples are of the scene before it moved
+            accumulation.reset();
+        }
 
         // TODO: This is a hack.
         let obj = Object::new(Mesh::new(vec![triangle.clone()]));
         let mut scn = Scene::new_empty();
         scn.objects.push(obj);
-        let img = cam.render(&scn, (rend_size, rend_size));
+        scn.lights.push(Box::new(DirectionalLight::new(
+            YaprePoint::new(0.0, -0.5, 1.0),
+            WHITE,
+            1.0
+        )));
+        let img = if paused {
+            accumulation.add_pass(&cam, &scn);
+            accumulation.image(&scn.render_settings.tone_mapping)
+        } else {
+            let context = RenderContext::new_default();
+            let img = preview.render_with_context(&scn, (rend_size, rend_size), &context);
+            last_stats = context.stats();
+            img
+        };
         for x in 0..rend_size {
             for y in 0..rend_size {
                 let pix = img.get_pixel(y, x).color.clone();